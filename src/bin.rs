@@ -7,11 +7,11 @@ use io::Write;
 use rfyl::roll as roll;
 use time::PreciseTime;
 
-const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() {
     println!("Running rfyl version {}", VERSION);
-    println!("");
+    println!();
     io::stdout().flush().expect("[Error] Flush failed!");
     let args: Vec<_> = env::args().collect();
     if args.len() > 1 {
@@ -30,11 +30,24 @@ fn main() {
                 exit(1);
             }
         };
+        let result = match roll.get_result_checked() {
+            Ok(v) => v,
+            Err(e) => {
+                println!("[Error] Invalid input: {}", e);
+                exit(1);
+            }
+        };
         println!("------------------------------------------");        
         println!("Rolls:             {}", roll.get_rolls_string());
         println!("Formula:           {}", roll.get_formula_string_as_infix());
         println!("Rolls Formula:     {}", roll.get_rolls_formula_string_as_infix());
-        println!("Result:            {}", roll.get_result());
+        println!("Result:            {}", result);
+        if let Some(successes) = roll.get_successes() {
+            println!("Successes:         {}", successes);
+        }
+        if let Some(succeeded) = roll.succeeded() {
+            println!("Succeeded:         {}", succeeded);
+        }
         println!("------------------------------------------");
         let end = PreciseTime::now();        
         println!("Execution Time:    {}", start.to(end));
@@ -54,11 +67,24 @@ fn main() {
                                 continue;
                             }
                         };
+                        let result = match roll.get_result_checked() {
+                            Ok(v) => v,
+                            Err(e) => {
+                                println!("[Error] Invalid input: {}", e);
+                                continue;
+                            }
+                        };
                         println!("------------------------------------------");
                         println!("Rolls:             {}", roll.get_rolls_string());
                         println!("Formula:           {}", roll.get_formula_string_as_infix());
                         println!("Rolls Formula:     {}", roll.get_rolls_formula_string_as_infix());
-                        println!("Result:            {}", roll.get_result());
+                        println!("Result:            {}", result);
+                        if let Some(successes) = roll.get_successes() {
+                            println!("Successes:         {}", successes);
+                        }
+                        if let Some(succeeded) = roll.succeeded() {
+                            println!("Succeeded:         {}", succeeded);
+                        }
                         println!("------------------------------------------");
                         let end = PreciseTime::now();                        
                         println!("Execution Time:    {}", start.to(end));
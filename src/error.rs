@@ -0,0 +1,127 @@
+//! Provides the error type returned when a roll cannot be performed.
+use alloc::fmt;
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+/// The reasons a formula can fail to roll.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RollError {
+    /// The input contained nothing to roll.
+    EmptyExpression,
+    /// An operator was missing one or both of its operands, e.g. `+` or `2d6 *`.
+    MissingOperand(String),
+    /// Two values were left without an operator joining them, e.g. `(2d6)(1d4)`.
+    MissingOperator,
+    /// A term of a postfix (rpn) formula being solved is neither a whole number nor an
+    /// operator, e.g. the `2d6` of `["2d6", "3", "+"]`.
+    InvalidTerm(String),
+    /// An operator came straight after another, e.g. the second `+` of `2d6 + + 3`.
+    /// `position` is the character offset of the second operator in the input.
+    UnexpectedOperator { operator: String, position: usize },
+    /// A bracket has no partner. `position` is the character offset of the offending
+    /// bracket in the input, and `parenthesis` is the bracket itself.
+    UnbalancedParentheses { position: usize, parenthesis: char },
+    /// A formula and its original notation don't have the same shape.
+    MismatchedFormulas,
+    /// A term could not be read as a constant or as dice notation. `position` is the
+    /// character offset of the term in the input, or of the term it is bracketed within.
+    /// (For a formula given to `DiceRolls::from_parts()`, it is the index of the term.)
+    InvalidFragment { fragment: String, position: usize },
+    /// Average dice were asked for with other than six sides, e.g. `3d8a`. `position` is
+    /// the character offset of the term in the input.
+    InvalidAverageDice { fragment: String, position: usize },
+    /// Dice were asked for with no sides or a negative number of sides, e.g. `3d0` or
+    /// `2d(1 - 3)`. `position` is the character offset of the term in the input.
+    InvalidDiceSides { fragment: String, position: usize },
+    /// A replayed die was given a face it can't show, e.g. `100` for a `d6`. `position` is
+    /// the character offset of the face in the input.
+    ImpossibleFace { face: i32, position: usize },
+    /// The formula divided by zero, e.g. `1d6 / 0`.
+    DivisionByZero,
+    /// A result was too large or too small to be held as an i32.
+    Overflow,
+    /// Advantage or disadvantage was asked for, but the formula has no plain `d20` to roll twice.
+    MissingD20,
+    /// Rolling would take more operations than `RollOptions::max_operations` allows.
+    ComputationLimit,
+}
+
+impl fmt::Display for RollError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RollError::EmptyExpression => write!(f, "the formula is empty"),
+            RollError::MissingOperand(ref operator) => {
+                write!(f, "the operator `{}` is missing an operand", operator)
+            }
+            RollError::MissingOperator => write!(f, "two values are missing an operator between them"),
+            RollError::InvalidTerm(ref term) => write!(f, "`{}` is not a number or an operator", term),
+            RollError::UnexpectedOperator { ref operator, position } => {
+                write!(f, "the operator `{}` at position {} follows another operator", operator, position)
+            }
+            RollError::UnbalancedParentheses { position, parenthesis: '(' } => {
+                write!(f, "the `(` at position {} is never closed", position)
+            }
+            RollError::UnbalancedParentheses { position, parenthesis } => {
+                write!(f, "the `{}` at position {} has no matching `(`", parenthesis, position)
+            }
+            RollError::MismatchedFormulas => write!(f, "the formula doesn't match its notation"),
+            RollError::InvalidFragment { ref fragment, position } => {
+                write!(f, "`{}` at position {} is not a valid constant or dice notation", fragment, position)
+            }
+            RollError::InvalidAverageDice { ref fragment, position } => {
+                write!(f, "`{}` at position {} asks for average dice, which only have six sides", fragment, position)
+            }
+            RollError::InvalidDiceSides { ref fragment, position } => {
+                write!(f, "`{}` at position {} asks for dice with fewer than one side", fragment, position)
+            }
+            RollError::ImpossibleFace { face, position } => {
+                write!(f, "the face `{}` at position {} can't be shown by its die", face, position)
+            }
+            RollError::DivisionByZero => write!(f, "the formula divides by zero"),
+            RollError::Overflow => write!(f, "the result is too large to hold"),
+            RollError::MissingD20 => write!(f, "the formula has no d20 to roll twice"),
+            RollError::ComputationLimit => write!(f, "the formula takes too many operations to roll"),
+        }
+    }
+}
+
+impl RollError {
+    /// Returns the character offset in the input that the error points at, if it has one.
+    pub fn position(&self) -> Option<usize> {
+        match *self {
+            RollError::UnbalancedParentheses { position, .. }
+            | RollError::UnexpectedOperator { position, .. }
+            | RollError::InvalidFragment { position, .. }
+            | RollError::InvalidAverageDice { position, .. }
+            | RollError::InvalidDiceSides { position, .. }
+            | RollError::ImpossibleFace { position, .. } => Some(position),
+            _ => None,
+        }
+    }
+
+    /// Returns the error pointing at `position` instead, if it points anywhere.
+    pub(crate) fn at(mut self, to: usize) -> RollError {
+        match self {
+            RollError::UnbalancedParentheses { ref mut position, .. }
+            | RollError::UnexpectedOperator { ref mut position, .. }
+            | RollError::InvalidFragment { ref mut position, .. }
+            | RollError::InvalidAverageDice { ref mut position, .. }
+            | RollError::InvalidDiceSides { ref mut position, .. }
+            | RollError::ImpossibleFace { ref mut position, .. } => *position = to,
+            _ => {}
+        }
+        self
+    }
+
+    /// Returns the error pointing `by` characters further on, if it points anywhere.
+    pub(crate) fn offset(self, by: usize) -> RollError {
+        match self.position() {
+            Some(position) => self.at(position + by),
+            None => self,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for RollError {}
@@ -0,0 +1,499 @@
+//! Provides facilities for reading the modifiers that apply to a whole formula, such as `dc15`.
+//!
+//! These are written at the end of the formula, after the dice and operators, e.g.
+//! `1d20 + 5 dc15`:
+//!
+//! - `dc<n>` or `vs<n>` sets a difficulty class which the result must meet to succeed.
+//! - `half` halves the result, rounding down, and `halfup` halves it rounding up.
+//! - `crit<n>` makes a result of `n` or more a critical success, and `fumble<n>` makes a
+//!   result of `n` or less a critical failure. These are read along with any difficulty
+//!   class by [DiceRolls::get_success_tier()](../struct.DiceRolls.html#method.get_success_tier).
+//!
+//! A formula that has been read, or put together with an ExpressionBuilder, is held as a
+//! ParsedExpression until it is rolled.
+use alloc::str::FromStr;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use error::RollError;
+use fragment::parse_fragment;
+use options::RoundingMode;
+use rpn::{parse_tokens_into_rpn, rearrange_into_rpn, validate_rpn_formula};
+use tokens::{match_token, Token};
+
+/// The modifiers that apply to a whole formula.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExpressionModifiers {
+    pub dc: Option<i32>,
+    /// How the result is rounded when it is halved, if it is.
+    pub half: Option<RoundingMode>,
+    /// The lowest result that is a critical success.
+    pub crit: Option<i32>,
+    /// The highest result that is a critical failure.
+    pub fumble: Option<i32>,
+}
+
+/// A formula that is ready to be rolled with [evaluate()](fn.evaluate.html), either read
+/// from dice notation or put together with an [ExpressionBuilder](struct.ExpressionBuilder.html).
+///
+/// Two expressions are equal when they have the same formula and modifiers, wherever
+/// their terms were written in the input.
+#[derive(Clone, Debug)]
+pub struct ParsedExpression {
+    formula: Vec<String>,
+    /// The character offset in the input of each element of `formula`.
+    positions: Vec<usize>,
+    modifiers: ExpressionModifiers,
+}
+
+impl PartialEq for ParsedExpression {
+    fn eq(&self, other: &ParsedExpression) -> bool {
+        self.formula == other.formula && self.modifiers == other.modifiers
+    }
+}
+
+impl ParsedExpression {
+    /// Returns the formula in postfix (rpn) format, e.g. `["2d6", "3", "+"]`.
+    pub fn get_formula(&self) -> &[String] {
+        &self.formula
+    }
+
+    /// Returns the character offset in the input of each element of the formula.
+    pub(crate) fn get_positions(&self) -> &[usize] {
+        &self.positions
+    }
+
+    /// Returns the difficulty class the result must meet, if one was given.
+    pub fn get_dc(&self) -> Option<i32> {
+        self.modifiers.dc
+    }
+
+    /// Returns how the result is rounded when it is halved, if it is.
+    pub fn get_half(&self) -> Option<RoundingMode> {
+        self.modifiers.half
+    }
+
+    /// Returns the lowest result that is a critical success, if one was given with `crit<n>`.
+    pub fn get_crit(&self) -> Option<i32> {
+        self.modifiers.crit
+    }
+
+    /// Returns the highest result that is a critical failure, if one was given with `fumble<n>`.
+    pub fn get_fumble(&self) -> Option<i32> {
+        self.modifiers.fumble
+    }
+
+    /// Returns the expression with its first plain `d20` (or `1d20`) rolled twice, keeping
+    /// the higher die, as in `2d20kh1`. An expression without a plain d20 returns
+    /// `RollError::MissingD20`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::parse_expression;
+    ///
+    /// let expression = parse_expression("1d20 + 5").unwrap().with_advantage().unwrap();
+    /// assert_eq!(parse_expression("2d20kh1 + 5").unwrap(), expression);
+    /// ```
+    pub fn with_advantage(&self) -> Result<ParsedExpression, RollError> {
+        self.replace_first_d20("2d20kh1")
+    }
+
+    /// Returns the expression with its first plain `d20` (or `1d20`) rolled twice, keeping
+    /// the lower die, as in `2d20kl1`. An expression without a plain d20 returns
+    /// `RollError::MissingD20`.
+    pub fn with_disadvantage(&self) -> Result<ParsedExpression, RollError> {
+        self.replace_first_d20("2d20kl1")
+    }
+
+    fn replace_first_d20(&self, replacement: &str) -> Result<ParsedExpression, RollError> {
+        let position = self.formula.iter().position(|term| is_plain_d20(term)).ok_or(RollError::MissingD20)?;
+        let mut expression = self.clone();
+        expression.formula[position] = replacement.to_string();
+        Ok(expression)
+    }
+}
+
+/// Reads a ParsedExpression from dice notation, as with [parse_expression()](fn.parse_expression.html).
+///
+/// # Example
+///
+/// ```
+/// use rfyl::{evaluate, ParsedExpression};
+///
+/// let expression: ParsedExpression = "2d6 + 3".parse().unwrap();
+/// let result = evaluate(&expression).unwrap().get_result();
+/// assert!(result >= 5 && result <= 15);
+/// ```
+impl FromStr for ParsedExpression {
+    type Err = RollError;
+
+    fn from_str(input: &str) -> Result<ParsedExpression, RollError> {
+        parse_expression(input)
+    }
+}
+
+/// Returns whether a term is a single d20 without any modifiers.
+fn is_plain_d20(term: &str) -> bool {
+    parse_fragment(term).ok() == parse_fragment("1d20").ok()
+}
+
+/// Returns a ParsedExpression read from dice notation, without rolling it.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+pub fn parse_expression(input: &str) -> Result<ParsedExpression, RollError> {
+    let trimmed = input.trim_start();
+    let leading = input[..input.len() - trimmed.len()].chars().count();
+    let (input, modifiers) = split_modifiers(trimmed.trim_end());
+    if input.is_empty() {
+        return Err(RollError::EmptyExpression);
+    }
+
+    let tokens = parse_tokens_into_rpn(input).map_err(|e| e.offset(leading))?;
+    let (formula, positions): (Vec<String>, Vec<usize>) =
+        tokens.into_iter().map(|token| (token.text, token.position + leading)).unzip();
+    validate_rpn_formula(&formula)?;
+    Ok(ParsedExpression {
+        formula,
+        positions,
+        modifiers,
+    })
+}
+
+/// Returns a ParsedExpression read from tokens that have already been split up, in the
+/// order they were written, for callers that do their own lexing. Each token must be an
+/// operator, a bracket, or a single term such as `2d6` or `3`, and is trimmed of whitespace.
+/// Formula modifiers such as `dc15` can't be given this way.
+///
+/// Errors give the index of the token they were found at, rather than a character offset.
+///
+/// # Arguments
+/// * `tokens` - The tokens of the formula, e.g. `["(", "2d6", "+", "3", ")", "*", "2"]`.
+///
+/// # Example
+///
+/// ```
+/// use rfyl::{parse_expression, parse_tokens};
+///
+/// let tokens = vec!["2d6".to_string(), "+".to_string(), "3".to_string()];
+/// assert_eq!(parse_expression("2d6 + 3").unwrap(), parse_tokens(tokens).unwrap());
+/// ```
+pub fn parse_tokens<I: IntoIterator<Item = String>>(tokens: I) -> Result<ParsedExpression, RollError> {
+    let mut read: Vec<Token> = Vec::new();
+    for (position, token) in tokens.into_iter().enumerate() {
+        let text = token.trim().to_string();
+        let precedence = match_token(&text);
+        if precedence == 0 || precedence == -3 {
+            parse_fragment(&text).map_err(|e| e.at(position))?;
+        }
+        read.push(Token { text, position });
+    }
+    if read.is_empty() {
+        return Err(RollError::EmptyExpression);
+    }
+
+    let (formula, positions): (Vec<String>, Vec<usize>) =
+        rearrange_into_rpn(read)?.into_iter().map(|token| (token.text, token.position)).unzip();
+    validate_rpn_formula(&formula)?;
+    Ok(ParsedExpression {
+        formula,
+        positions,
+        modifiers: ExpressionModifiers::default(),
+    })
+}
+
+/// Puts together a ParsedExpression one term at a time, without writing dice notation.
+///
+/// Each operator applies to everything before it, so terms are combined from left to
+/// right regardless of the usual precedence.
+///
+/// # Example
+///
+/// ```
+/// use rfyl::{evaluate, ExpressionBuilder};
+///
+/// // (2d6 + 3) * 2
+/// let expression = ExpressionBuilder::new().dice(2, 6).plus_constant(3).times_constant(2).build();
+/// let result = evaluate(&expression).unwrap().get_result();
+/// assert!(result >= 10 && result <= 30);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExpressionBuilder {
+    formula: Vec<String>,
+    modifiers: ExpressionModifiers,
+}
+
+impl ExpressionBuilder {
+    /// Returns a builder with no terms.
+    pub fn new() -> ExpressionBuilder {
+        ExpressionBuilder::default()
+    }
+
+    /// Starts the expression with `count` dice of `sides` sides. Later calls add to the expression.
+    pub fn dice(self, count: i32, sides: i32) -> ExpressionBuilder {
+        self.plus_dice(count, sides)
+    }
+
+    /// Starts the expression with a constant. Later calls add to the expression.
+    pub fn constant(self, value: i32) -> ExpressionBuilder {
+        self.plus_constant(value)
+    }
+
+    /// Adds `count` dice of `sides` sides.
+    pub fn plus_dice(self, count: i32, sides: i32) -> ExpressionBuilder {
+        self.push(dice_term(count, sides), "+")
+    }
+
+    /// Adds a constant.
+    pub fn plus_constant(self, value: i32) -> ExpressionBuilder {
+        self.push(value.to_string(), "+")
+    }
+
+    /// Subtracts `count` dice of `sides` sides.
+    pub fn minus_dice(self, count: i32, sides: i32) -> ExpressionBuilder {
+        self.push(dice_term(count, sides), "-")
+    }
+
+    /// Subtracts a constant.
+    pub fn minus_constant(self, value: i32) -> ExpressionBuilder {
+        self.push(value.to_string(), "-")
+    }
+
+    /// Multiplies the expression so far by `count` dice of `sides` sides.
+    pub fn times_dice(self, count: i32, sides: i32) -> ExpressionBuilder {
+        self.push(dice_term(count, sides), "*")
+    }
+
+    /// Multiplies the expression so far by a constant.
+    pub fn times_constant(self, value: i32) -> ExpressionBuilder {
+        self.push(value.to_string(), "*")
+    }
+
+    /// Divides the expression so far by `count` dice of `sides` sides.
+    pub fn divided_by_dice(self, count: i32, sides: i32) -> ExpressionBuilder {
+        self.push(dice_term(count, sides), "/")
+    }
+
+    /// Divides the expression so far by a constant.
+    pub fn divided_by_constant(self, value: i32) -> ExpressionBuilder {
+        self.push(value.to_string(), "/")
+    }
+
+    /// Sets a difficulty class which the result must meet to succeed, as `dc<n>` does.
+    pub fn dc(mut self, dc: i32) -> ExpressionBuilder {
+        self.modifiers.dc = Some(dc);
+        self
+    }
+
+    /// Halves the result, rounded with `rounding`, as `half` (`RoundingMode::Floor`) and
+    /// `halfup` (`RoundingMode::Ceil`) do.
+    pub fn halved(mut self, rounding: RoundingMode) -> ExpressionBuilder {
+        self.modifiers.half = Some(rounding);
+        self
+    }
+
+    /// Makes a result of `crit` or more a critical success, as `crit<n>` does.
+    pub fn crit(mut self, crit: i32) -> ExpressionBuilder {
+        self.modifiers.crit = Some(crit);
+        self
+    }
+
+    /// Makes a result of `fumble` or less a critical failure, as `fumble<n>` does.
+    pub fn fumble(mut self, fumble: i32) -> ExpressionBuilder {
+        self.modifiers.fumble = Some(fumble);
+        self
+    }
+
+    /// Returns the finished expression. A builder with no terms gives an expression that
+    /// fails to evaluate with `RollError::EmptyExpression`.
+    pub fn build(self) -> ParsedExpression {
+        ParsedExpression {
+            positions: vec![0; self.formula.len()],
+            formula: self.formula,
+            modifiers: self.modifiers,
+        }
+    }
+
+    /// Adds a term, joined to the terms before it (if any) by `operator`.
+    fn push(mut self, term: String, operator: &str) -> ExpressionBuilder {
+        let first = self.formula.is_empty();
+        self.formula.push(term);
+        if !first {
+            self.formula.push(operator.to_string());
+        }
+        self
+    }
+}
+
+fn dice_term(count: i32, sides: i32) -> String {
+    format!("{}d{}", count, sides)
+}
+
+/// Returns the formula with any trailing expression modifiers removed, along with the
+/// modifiers that were found. Keywords are matched case-insensitively, may come in any
+/// order, and may be separated from the formula and from their numbers by whitespace.
+///
+/// # Example values
+///
+/// * `1d20 + 5 dc15` -> `1d20 + 5`, `dc: Some(15)`
+/// * `1d20 + 5 VS 12` -> `1d20 + 5`, `dc: Some(12)`
+/// * `2d6 + 3 half dc5` -> `2d6 + 3`, `dc: Some(5)`, `half: Some(RoundingMode::Floor)`
+/// * `1d20 + 5 dc15 crit25` -> `1d20 + 5`, `dc: Some(15)`, `crit: Some(25)`
+/// * `2d6` -> `2d6`, `dc: None`
+pub fn split_modifiers(input: &str) -> (&str, ExpressionModifiers) {
+    let mut formula = input.trim_end();
+    let mut modifiers = ExpressionModifiers::default();
+
+    loop {
+        if let Some((rest, dc)) = strip_keyword_number(formula, "dc").or_else(|| strip_keyword_number(formula, "vs")) {
+            if modifiers.dc.is_some() {
+                break;
+            }
+            formula = rest;
+            modifiers.dc = Some(dc);
+        } else if let Some((rest, rounding)) = strip_half(formula) {
+            if modifiers.half.is_some() {
+                break;
+            }
+            formula = rest;
+            modifiers.half = Some(rounding);
+        } else if let Some((rest, crit)) = strip_keyword_number(formula, "crit") {
+            if modifiers.crit.is_some() {
+                break;
+            }
+            formula = rest;
+            modifiers.crit = Some(crit);
+        } else if let Some((rest, fumble)) = strip_keyword_number(formula, "fumble") {
+            if modifiers.fumble.is_some() {
+                break;
+            }
+            formula = rest;
+            modifiers.fumble = Some(fumble);
+        } else {
+            break;
+        }
+    }
+
+    (formula, modifiers)
+}
+
+/// Strips a trailing `half` or `halfup` from `input`, returning what remains and how the
+/// halved result is rounded.
+fn strip_half(input: &str) -> Option<(&str, RoundingMode)> {
+    for &(keyword, rounding) in &[("halfup", RoundingMode::Ceil), ("half", RoundingMode::Floor)] {
+        if input.len() >= keyword.len() && input.is_char_boundary(input.len() - keyword.len()) {
+            let (rest, found) = input.split_at(input.len() - keyword.len());
+            if found.eq_ignore_ascii_case(keyword) {
+                return Some((rest.trim_end(), rounding));
+            }
+        }
+    }
+    None
+}
+
+/// Strips a trailing `<keyword><number>` from `input`, returning what remains and the number.
+fn strip_keyword_number<'a>(input: &'a str, keyword: &str) -> Option<(&'a str, i32)> {
+    let digits_start = input.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let number = input[digits_start..].parse::<i32>().ok()?;
+    let before = input[..digits_start].trim_end();
+    if before.len() < keyword.len() || !before.is_char_boundary(before.len() - keyword.len()) {
+        return None;
+    }
+    let (rest, found) = before.split_at(before.len() - keyword.len());
+    if !found.eq_ignore_ascii_case(keyword) {
+        return None;
+    }
+    Some((rest.trim_end(), number))
+}
+
+#[test]
+fn split_difficulty_class() {
+    let dc = |dc: i32| ExpressionModifiers { dc: Some(dc), ..ExpressionModifiers::default() };
+    assert_eq!(("1d20 + 5", dc(15)), split_modifiers("1d20 + 5 dc15"));
+    assert_eq!(("1d20+5", dc(15)), split_modifiers("1d20+5dc15"));
+    assert_eq!(("1d20 + 5", dc(12)), split_modifiers("1d20 + 5 VS 12 "));
+    assert_eq!(("2d6 + 15", ExpressionModifiers::default()), split_modifiers("2d6 + 15"));
+    assert_eq!(("2d6 dc", ExpressionModifiers::default()), split_modifiers("2d6 dc"));
+}
+
+#[test]
+fn split_success_tiers() {
+    let tiers = |dc: Option<i32>, crit: Option<i32>, fumble: Option<i32>| ExpressionModifiers {
+        dc,
+        crit,
+        fumble,
+        ..ExpressionModifiers::default()
+    };
+    assert_eq!(("1d20+5", tiers(Some(15), Some(25), None)), split_modifiers("1d20+5 dc15 crit25"));
+    assert_eq!(("1d20", tiers(Some(10), Some(20), Some(1))), split_modifiers("1d20 fumble1 CRIT 20 dc10"));
+    assert_eq!(("2d6 crit5", tiers(None, Some(6), None)), split_modifiers("2d6 crit5 crit6"));
+}
+
+#[test]
+fn split_halving() {
+    let half = |rounding: RoundingMode, dc: Option<i32>| ExpressionModifiers {
+        dc,
+        half: Some(rounding),
+        ..ExpressionModifiers::default()
+    };
+    assert_eq!(("2d6 + 3", half(RoundingMode::Floor, None)), split_modifiers("2d6 + 3 half"));
+    assert_eq!(("2d6+3", half(RoundingMode::Ceil, None)), split_modifiers("2d6+3HalfUp"));
+    assert_eq!(("2d6 + 3", half(RoundingMode::Floor, Some(5))), split_modifiers("2d6 + 3 half dc5"));
+    assert_eq!(("2d6 + 3", half(RoundingMode::Ceil, Some(5))), split_modifiers("2d6 + 3 dc5 halfup"));
+    assert_eq!(("2d6 half", half(RoundingMode::Floor, None)), split_modifiers("2d6 half half"));
+    assert_eq!(
+        Ok(ExpressionBuilder::new().dice(2, 6).plus_constant(3).halved(RoundingMode::Ceil).build()),
+        parse_expression("2d6 + 3 halfup")
+    );
+}
+
+#[test]
+fn build_expressions() {
+    assert_eq!(Ok(ExpressionBuilder::new().dice(2, 6).plus_constant(3).build()), parse_expression("2d6 + 3"));
+    assert_eq!(
+        Ok(ExpressionBuilder::new().constant(10).minus_dice(1, 4).times_constant(2).dc(12).build()),
+        parse_expression("(10 - 1d4) * 2 dc12")
+    );
+    assert_eq!(
+        vec!["1d8", "2d4", "/", "1d6", "+"],
+        ExpressionBuilder::new().dice(1, 8).divided_by_dice(2, 4).dice(1, 6).build().get_formula()
+    );
+    assert!(ExpressionBuilder::new().build().get_formula().is_empty());
+}
+
+#[test]
+fn parse_from_str() {
+    assert_eq!(parse_expression("2d6 + 3 dc10"), "2d6 + 3 dc10".parse::<ParsedExpression>());
+    assert_eq!(Err(RollError::EmptyExpression), "  ".parse::<ParsedExpression>());
+}
+
+#[test]
+fn roll_d20_twice() {
+    let expression = parse_expression("3 + d20 + 1d20 dc15").unwrap();
+    assert_eq!(parse_expression("3 + 2d20kl1 + 1d20 dc15"), expression.with_disadvantage());
+    assert_eq!(
+        Err(RollError::MissingD20),
+        parse_expression("2d20 + 1d20!").unwrap().with_advantage()
+    );
+}
+
+#[test]
+fn parse_split_tokens() {
+    let tokens = |texts: &[&str]| parse_tokens(texts.iter().map(|text| text.to_string()));
+    assert_eq!(parse_expression("(2d6 + 3) * 2"), tokens(&["(", "2d6", "+", " 3 ", ")", "*", "2"]));
+    assert_eq!(parse_expression("1d20 - -2"), tokens(&["1d20", "-", "-2"]));
+    assert_eq!(
+        Err(RollError::InvalidFragment { fragment: "2d6+3".to_string(), position: 1 }),
+        tokens(&["1d4", "2d6+3"]).map(|_| ())
+    );
+    assert_eq!(
+        Err(RollError::UnexpectedOperator { operator: "*".to_string(), position: 2 }),
+        tokens(&["1d4", "+", "*", "2"]).map(|_| ())
+    );
+    assert_eq!(
+        Err(RollError::UnbalancedParentheses { position: 0, parenthesis: '(' }),
+        tokens(&["(", "1d4"]).map(|_| ())
+    );
+    assert_eq!(Err(RollError::EmptyExpression), tokens(&[]).map(|_| ()));
+    assert_eq!(Err(RollError::MissingOperator), tokens(&["1d4", "2"]).map(|_| ()));
+}
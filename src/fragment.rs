@@ -0,0 +1,946 @@
+//! Provides facilities for reading a single term of a formula, such as `12` or `6d10>=7f1`.
+//!
+//! The count and the sides of some dice can each be given as a bracketed formula, as in
+//! `(1d4)d6` or `2d(1d4 + 2)`, which is rolled first to decide how many or how large the
+//! dice are.
+//!
+//! A number written directly before a bracketed formula, as in `3(1d6 + 2)`, repeats it:
+//! the formula is rolled that many times and the results added together. A negative repeat,
+//! as in `-2(1d6)`, takes the total away instead, and a repeat of zero rolls nothing.
+//!
+//! Six-sided average dice, with the faces `2, 3, 3, 4, 4, 5`, are written as `da` or `d6a`, and
+//! percentile tens dice, with the faces `0, 10, ..., 90`, are written as `d00`. `d66` and `d666`
+//! roll two or three d6s and read them as the digits of a single result, as many tables do.
+//! A die with 66 or 666 sides can still be rolled by bracketing its sides, as in `d(66)`.
+//!
+//! The sides may be left out, as in `3d`, to roll dice with the `default_sides` option.
+//!
+//! Dice modifiers are written after the sides, in the same order as they are applied:
+//!
+//! 1. A reroll target (`r1`, `r<3`), where each matching die is rerolled until it doesn't match.
+//!    Alternatively `rh1`, where a matching die is rerolled once and the new roll is kept even
+//!    if it is lower, or `rk1`, where a matching die is rerolled once and the higher is kept.
+//! 2. `!`, where each die showing its maximum face explodes: another die is rolled and added.
+//!    Alternatively `p`, where each die penetrates, as with `!` but with each additional die
+//!    counting one less than it shows. Either may be followed by a comparison, as in `!>=5`,
+//!    for the dice to explode on any face that passes it rather than only on the maximum.
+//! 3. `e<n>` (or `e-<n>`), where `n` is added to (or taken from) each die rather than to the
+//!    sum. Then `cap<n>`, where each die counts for no more than `n`, and then `floor<n>`,
+//!    where each die counts for no less than `n`. Explosions still happen on the face a die
+//!    shows.
+//! 4. A selection, keeping (`kh3`, `kl1`) or dropping (`dh1`, `dl1`) the highest or lowest dice.
+//!    The number may be left out to keep or drop a single die.
+//! 5. A success target (`>=7`, `>7`, `<=2`, `<2`, `=6`), which turns the dice into a pool.
+//! 6. A failure target (`f1`, `f<=2`), where each matching die subtracts a success.
+//! 7. `dbl`, where each die showing its maximum face counts as two successes.
+//!
+//! The failure target and `dbl` are only accepted after a success target.
+//!
+//! Dice written with a leading `-`, as in `-2d4`, are negative: each die counts against the
+//! total, after all of its modifiers have been applied. Negative dice can't count successes.
+//!
+//! A conditional, as in `1d20>=15 ? 2d6 : 0`, rolls the formula before the `?` first. If it
+//! counts any successes, or has no dice pools and a result other than `0`, the formula
+//! between the `?` and the `:` is rolled, and otherwise the formula after the `:` is rolled,
+//! so the dice of the other are never rolled. `?` and `:` bind more loosely than any
+//! operator, so a conditional takes in the whole formula unless it is bracketed, as in
+//! `(1d20>=15 ? 2d6 : 0) + 3`, and `a ? b : c ? d : e` reads as `a ? b : (c ? d : e)`.
+//!
+//! Several terms can be rolled together as a set by writing them in braces, as in
+//! `{4d6, 1d8}kh3`. A set may be followed by a selection, which then keeps or drops dice
+//! from across all of its terms. The terms of a set may not count successes or hold other sets.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use error::RollError;
+use tokens::{find_closing_bracket, find_top_level};
+
+/// A single term of a formula.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fragment {
+    /// A fixed number, e.g. `12`.
+    Constant(i32),
+    /// Dice notation, e.g. `2d6`.
+    Dice(DiceFragment),
+    /// Terms rolled together in braces, e.g. `{4d6, 1d8}kh3`.
+    Set(DiceSet),
+    /// A bracketed formula rolled several times, e.g. `3(1d6 + 2)`.
+    Repeat(Repeat),
+    /// A formula that chooses which of two others is rolled, e.g. `1d20>=15 ? 2d6 : 0`.
+    Conditional(Conditional),
+}
+
+/// A formula rolled to choose whether `then` or `otherwise` is rolled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conditional {
+    pub condition: String,
+    pub then: String,
+    pub otherwise: String,
+}
+
+/// A bracketed formula to be rolled several times, with the results added together, or
+/// taken away for a negative number of times.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Repeat {
+    pub times: i32,
+    pub expression: String,
+}
+
+/// Terms written together in braces, whose dice are kept or dropped as one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiceSet {
+    pub terms: Vec<Fragment>,
+    pub selection: Option<Selection>,
+}
+
+/// Dice notation along with any modifiers that apply to it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiceFragment {
+    pub count: Amount,
+    pub sides: Amount,
+    pub kind: DieKind,
+    pub reroll: Option<Reroll>,
+    pub explosion: Option<Explosion>,
+    /// The faces that explode, as with the `>=5` of `3d6!>=5`, or None for only the highest.
+    pub explode_on: Option<Comparison>,
+    pub each: Option<i32>,
+    pub cap: Option<i32>,
+    pub floor: Option<i32>,
+    pub selection: Option<Selection>,
+    pub pool: Option<Pool>,
+    /// Whether each die counts against the total, written with a leading `-`, as in `-2d4`.
+    pub negative: bool,
+}
+
+/// The faces of a die.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DieKind {
+    /// Faces from `1` up to the number of sides.
+    Standard,
+    /// The six faces `2, 3, 3, 4, 4, 5` of an average die.
+    Average,
+    /// The ten faces `0, 10, ..., 90` of a percentile tens die.
+    Tens,
+    /// A d6 for each digit of the sides, read as a single number, e.g. `11` to `66` for `d66`.
+    Digits,
+    /// Faces from `1` up to `100`, written `d%`, which may be rolled as a tens die and a ones die.
+    Percentile,
+    /// Faces from `0` up to one less than the number of sides, which standard and percentile
+    /// dice have when rolled with the `zero_indexed_dice` option.
+    ZeroIndexed,
+}
+
+/// The faces of a six-sided average die, in order.
+pub const AVERAGE_DIE_FACES: [i32; 6] = [2, 3, 3, 4, 4, 5];
+
+/// How dice showing their maximum face add further dice.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Explosion {
+    /// Each additional die counts as rolled, written `!`.
+    Standard,
+    /// Each additional die counts one less than rolled, written `p`.
+    Penetrating,
+}
+
+/// How dice matching a target are rolled again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reroll {
+    pub target: Comparison,
+    pub rule: RerollRule,
+}
+
+/// Which roll of a rerolled die is kept.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RerollRule {
+    /// The die is rerolled for as long as it matches, written `r`.
+    Until,
+    /// The die is rerolled once, and the new roll is kept, written `rh`.
+    KeepNew,
+    /// The die is rerolled once, and the higher of the two rolls is kept, written `rk`.
+    KeepHigher,
+}
+
+/// Which dice are kept after rolling, by their results.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+    KeepHighest(i32),
+    KeepLowest(i32),
+    DropHighest(i32),
+    DropLowest(i32),
+}
+
+/// The count or sides of some dice.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Amount {
+    /// A number written directly, e.g. the `2` and `6` of `2d6`.
+    Fixed(i32),
+    /// A bracketed formula to be rolled, e.g. the `1d4` of `(1d4)d6`.
+    Expression(String),
+    /// Sides left out, as in `3d`, which are given by the `default_sides` option.
+    Default,
+}
+
+/// The rules for counting successes rather than summing dice.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pool {
+    pub success: Comparison,
+    pub failure: Option<Comparison>,
+    pub double_on_max: bool,
+}
+
+impl Pool {
+    /// Returns the number of successes a single die contributes to the pool.
+    pub fn successes(&self, sides: i32, result: i32) -> i32 {
+        if !self.success.matches(result) {
+            0
+        } else if self.double_on_max && result == sides {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Returns the number of failures a single die contributes to the pool.
+    pub fn failures(&self, result: i32) -> i32 {
+        match self.failure {
+            Some(failure) if failure.matches(result) => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// A test applied to the result of a single die.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Equal(i32),
+    Greater(i32),
+    GreaterOrEqual(i32),
+    Less(i32),
+    LessOrEqual(i32),
+}
+
+impl Comparison {
+    /// Returns whether the given die result passes the test.
+    pub fn matches(&self, value: i32) -> bool {
+        match *self {
+            Comparison::Equal(target) => value == target,
+            Comparison::Greater(target) => value > target,
+            Comparison::GreaterOrEqual(target) => value >= target,
+            Comparison::Less(target) => value < target,
+            Comparison::LessOrEqual(target) => value <= target,
+        }
+    }
+}
+
+/// Returns the Fragment described by a single term of a formula.
+///
+/// # Arguments
+/// * `input_fragment` - A string containing a constant or dice notation, without operators.
+///
+/// # Example values
+///
+/// * `12` -> `Constant(12)`
+/// * `1,000` -> `Constant(1000)`
+/// * `d%` -> `Dice { count: 1, sides: 100, pool: None }`
+/// * `6d10>=7f1` -> `Dice { count: 6, sides: 10, pool: Some(..) }`
+///
+/// Any tag after the term, as in `2d6[fire]`, is left out; see `split_tag()`.
+pub fn parse_fragment(input_fragment: &str) -> Result<Fragment, RollError> {
+    let written = input_fragment;
+    let input_fragment = split_tag(input_fragment).0;
+    if let Some(constant) = parse_constant(input_fragment) {
+        return Ok(Fragment::Constant(constant));
+    }
+
+    let invalid = || RollError::InvalidFragment {
+        fragment: written.to_string(),
+        position: 0,
+    };
+    let unbracketed = match find_closing_bracket(input_fragment) {
+        Some(close) if input_fragment.starts_with('(') && close == input_fragment.len() - 1 => {
+            &input_fragment[1..close]
+        }
+        _ => input_fragment,
+    };
+    if find_top_level(unbracketed, '?').is_some() {
+        return parse_conditional(unbracketed).map(Fragment::Conditional).ok_or_else(invalid);
+    }
+
+    let mut cursor = Cursor::new(input_fragment);
+    if let Some(terms) = cursor.braced() {
+        return parse_set(terms, &mut cursor).ok_or_else(invalid);
+    }
+    if let Some(repeat) = cursor.repeat() {
+        return Ok(Fragment::Repeat(repeat));
+    }
+
+    let negative = cursor.eat("-");
+    let count = if let Some(expression) = cursor.bracketed() {
+        Amount::Expression(expression.to_string())
+    } else if cursor.rest.starts_with(['d', 'D']) {
+        Amount::Fixed(1)
+    } else {
+        Amount::Fixed(cursor.number().ok_or_else(invalid)?)
+    };
+
+    // The dice are written with either a `d` or a `D`, as in `2D6`.
+    if !cursor.eat("d") && !cursor.eat("D") {
+        return Err(invalid());
+    }
+
+    let (sides, kind) = if cursor.eat("a") {
+        (Amount::Fixed(6), DieKind::Average)
+    } else if cursor.eat("00") {
+        (Amount::Fixed(10), DieKind::Tens)
+    } else if cursor.eat("%") {
+        (Amount::Fixed(100), DieKind::Percentile)
+    } else {
+        let sides = if let Some(expression) = cursor.bracketed() {
+            Amount::Expression(expression.to_string())
+        } else if let Some(sides) = cursor.number() {
+            Amount::Fixed(sides)
+        } else {
+            Amount::Default
+        };
+        let kind = if cursor.eat("a") {
+            DieKind::Average
+        } else if sides == Amount::Fixed(66) || sides == Amount::Fixed(666) {
+            DieKind::Digits
+        } else {
+            DieKind::Standard
+        };
+        (sides, kind)
+    };
+    if kind == DieKind::Average && sides != Amount::Fixed(6) {
+        return Err(RollError::InvalidAverageDice {
+            fragment: input_fragment.to_string(),
+            position: 0,
+        });
+    }
+
+    let reroll_rule = if cursor.eat("rh") {
+        Some(RerollRule::KeepNew)
+    } else if cursor.eat("rk") {
+        Some(RerollRule::KeepHigher)
+    } else if cursor.eat("r") {
+        Some(RerollRule::Until)
+    } else {
+        None
+    };
+    let reroll = match reroll_rule {
+        Some(rule) => Some(Reroll {
+            target: cursor.target().ok_or_else(invalid)?,
+            rule,
+        }),
+        None => None,
+    };
+
+    let explosion = if cursor.eat("!") {
+        Some(Explosion::Standard)
+    } else if cursor.eat("p") {
+        Some(Explosion::Penetrating)
+    } else {
+        None
+    };
+    let explode_on = if explosion.is_some() { cursor.comparison() } else { None };
+
+    let each = if cursor.eat("e") {
+        let sign = if cursor.eat("-") { -1 } else { 1 };
+        Some(sign * cursor.number().ok_or_else(invalid)?)
+    } else {
+        None
+    };
+
+    let cap = if cursor.eat("cap") {
+        Some(cursor.number().ok_or_else(invalid)?)
+    } else {
+        None
+    };
+
+    let floor = if cursor.eat("floor") {
+        Some(cursor.number().ok_or_else(invalid)?)
+    } else {
+        None
+    };
+
+    let selection = cursor.selection();
+
+    let pool = match cursor.comparison() {
+        Some(success) => {
+            let failure = if cursor.eat("f") {
+                Some(cursor.target().ok_or_else(invalid)?)
+            } else {
+                None
+            };
+            Some(Pool {
+                success,
+                failure,
+                double_on_max: cursor.eat("dbl"),
+            })
+        }
+        None => None,
+    };
+
+    // Negative dice count against the total, which successes can't.
+    if !cursor.is_empty() || (negative && pool.is_some()) {
+        return Err(invalid());
+    }
+
+    Ok(Fragment::Dice(DiceFragment {
+        count,
+        sides,
+        kind,
+        reroll,
+        explosion,
+        explode_on,
+        each,
+        cap,
+        floor,
+        selection,
+        pool,
+        negative,
+    }))
+}
+
+/// Returns the number written in `input`, which may have its digits grouped in threes
+/// by commas or thin spaces, as in `10,000,000`. Anything else, such as the `1,2` of
+/// what could be a list of arguments, is not a number.
+fn parse_constant(input: &str) -> Option<i32> {
+    if let Ok(constant) = input.parse::<i32>() {
+        return Some(constant);
+    }
+
+    let digits = input.trim_start_matches(['-', '+']);
+    let mut groups = digits.split([',', '\u{2009}']);
+    let first = groups.next()?;
+    if first.is_empty() || first.len() > 3 {
+        return None;
+    }
+    let mut number = String::from(&input[..input.len() - digits.len()]);
+    number.push_str(first);
+    for group in groups {
+        if group.len() != 3 {
+            return None;
+        }
+        number.push_str(group);
+    }
+    number.parse::<i32>().ok()
+}
+
+/// Splits the tag written after a term, as in `2d6[fire]`, from the term itself. A tag
+/// starts with a letter and has only letters, digits and `-` in it, so that it can't be
+/// confused with a list of values such as the `[4, 5]` of a replay string.
+///
+/// # Example values
+///
+/// * `2d6[fire]` -> `("2d6", Some("fire"))`
+/// * `3[cold-iron]` -> `("3", Some("cold-iron"))`
+/// * `2d6` -> `("2d6", None)`
+pub fn split_tag(input: &str) -> (&str, Option<&str>) {
+    let open = match input.strip_suffix(']').and_then(|rest| rest.rfind('[')) {
+        Some(open) if open > 0 => open,
+        _ => return (input, None),
+    };
+    let tag = &input[open + 1..input.len() - 1];
+    if tag.starts_with(|c: char| c.is_alphabetic()) && tag.chars().all(|c| c.is_alphanumeric() || c == '-') {
+        (&input[..open], Some(tag))
+    } else {
+        (input, None)
+    }
+}
+
+/// Returns the conditional written in `input`, split at its first `?` and at the `:` that
+/// goes with it, looking past any nested conditionals between them.
+fn parse_conditional(input: &str) -> Option<Conditional> {
+    let question = find_top_level(input, '?')?;
+    let rest = &input[question + 1..];
+    let mut depth = 0;
+    let mut nested = 0;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth -= 1,
+            '?' if depth == 0 => nested += 1,
+            ':' if depth == 0 && nested > 0 => nested -= 1,
+            ':' if depth == 0 => {
+                let parts = [&input[..question], &rest[..i], &rest[i + 1..]];
+                if parts.iter().any(|part| part.trim().is_empty()) {
+                    return None;
+                }
+                return Some(Conditional {
+                    condition: parts[0].trim().to_string(),
+                    then: parts[1].trim().to_string(),
+                    otherwise: parts[2].trim().to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the set made up of the comma separated `terms`, followed by the rest of `cursor`.
+fn parse_set(terms: &str, cursor: &mut Cursor) -> Option<Fragment> {
+    let mut set = DiceSet {
+        terms: Vec::new(),
+        selection: cursor.selection(),
+    };
+    if !cursor.is_empty() {
+        return None;
+    }
+
+    for term in terms.split(',') {
+        match parse_fragment(term.trim()).ok()? {
+            Fragment::Dice(ref dice) if dice.pool.is_some() => return None,
+            Fragment::Set(_) | Fragment::Repeat(_) | Fragment::Conditional(_) => return None,
+            fragment => set.terms.push(fragment),
+        }
+    }
+    Some(Fragment::Set(set))
+}
+
+/// Steps through the suffixes of a dice fragment.
+struct Cursor<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(rest: &'a str) -> Cursor<'a> {
+        Cursor { rest }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rest.is_empty()
+    }
+
+    /// Consumes `prefix` if the remaining input starts with it.
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest.starts_with(prefix) {
+            self.rest = &self.rest[prefix.len()..];
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes a run of ASCII digits.
+    fn number(&mut self) -> Option<i32> {
+        let end = self.rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(self.rest.len());
+        let number = self.rest[..end].parse::<i32>().ok()?;
+        self.rest = &self.rest[end..];
+        Some(number)
+    }
+
+    /// Consumes a bracketed formula, returning the formula without its brackets.
+    fn bracketed(&mut self) -> Option<&'a str> {
+        if !self.rest.starts_with('(') {
+            return None;
+        }
+        let close = find_closing_bracket(self.rest)?;
+        let expression = &self.rest[1..close];
+        self.rest = &self.rest[close + 1..];
+        Some(expression)
+    }
+
+    /// Consumes the whole of the remaining input if it is a repeated bracketed formula.
+    fn repeat(&mut self) -> Option<Repeat> {
+        let before = self.rest;
+        let sign = if self.eat("-") {
+            -1
+        } else {
+            self.eat("+");
+            1
+        };
+        if let Some(times) = self.number().map(|n| sign * n) {
+            if let Some(expression) = self.bracketed() {
+                if self.is_empty() {
+                    return Some(Repeat {
+                        times,
+                        expression: expression.to_string(),
+                    });
+                }
+            }
+        }
+        self.rest = before;
+        None
+    }
+
+    /// Consumes a set of terms in braces, returning the terms without their braces.
+    fn braced(&mut self) -> Option<&'a str> {
+        if !self.rest.starts_with('{') {
+            return None;
+        }
+        let close = self.rest.find('}')?;
+        let terms = &self.rest[1..close];
+        self.rest = &self.rest[close + 1..];
+        Some(terms)
+    }
+
+    /// Consumes a keep or drop modifier, whose number defaults to one.
+    fn selection(&mut self) -> Option<Selection> {
+        let build: fn(i32) -> Selection = if self.eat("kh") {
+            Selection::KeepHighest
+        } else if self.eat("kl") {
+            Selection::KeepLowest
+        } else if self.eat("dh") {
+            Selection::DropHighest
+        } else if self.eat("dl") {
+            Selection::DropLowest
+        } else {
+            return None;
+        };
+        Some(build(self.number().unwrap_or(1)))
+    }
+
+    /// Consumes a comparison, or a bare number to be matched exactly.
+    fn target(&mut self) -> Option<Comparison> {
+        match self.comparison() {
+            Some(comparison) => Some(comparison),
+            None => self.number().map(Comparison::Equal),
+        }
+    }
+
+    /// Consumes a comparison operator followed by its target number.
+    fn comparison(&mut self) -> Option<Comparison> {
+        let before = self.rest;
+        let build: fn(i32) -> Comparison = if self.eat(">=") {
+            Comparison::GreaterOrEqual
+        } else if self.eat("<=") {
+            Comparison::LessOrEqual
+        } else if self.eat(">") {
+            Comparison::Greater
+        } else if self.eat("<") {
+            Comparison::Less
+        } else if self.eat("=") {
+            Comparison::Equal
+        } else {
+            return None;
+        };
+        match self.number() {
+            Some(target) => Some(build(target)),
+            None => {
+                self.rest = before;
+                None
+            }
+        }
+    }
+}
+
+#[test]
+fn parse_dice_fragments() {
+    assert_eq!(Ok(Fragment::Constant(12)), parse_fragment("12"));
+    assert_eq!(Ok(Fragment::Constant(1000)), parse_fragment("1,000"));
+    assert_eq!(Ok(Fragment::Constant(10_000_000)), parse_fragment("10,000,000"));
+    assert_eq!(Ok(Fragment::Constant(-25_000)), parse_fragment("-25\u{2009}000"));
+    assert_eq!(Err(RollError::InvalidFragment { fragment: "1,2".to_string(), position: 0 }), parse_fragment("1,2"));
+    assert_eq!(Err(RollError::InvalidFragment { fragment: "1000,000".to_string(), position: 0 }), parse_fragment("1000,000"));
+    assert_eq!(parse_fragment("2d6"), parse_fragment("2D6"));
+    assert_eq!(parse_fragment("d20"), parse_fragment("D20"));
+    assert_eq!(parse_fragment("(1d4)d(6)kh1"), parse_fragment("(1d4)D(6)kh1"));
+    assert_eq!(
+        Ok(Fragment::Dice(DiceFragment {
+            count: Amount::Fixed(1),
+            sides: Amount::Fixed(100),
+            kind: DieKind::Percentile,
+            reroll: None,
+            explosion: None,
+            explode_on: None,
+            each: None,
+            cap: None,
+            floor: None,
+            selection: None,
+            pool: None,
+            negative: false,
+        })),
+        parse_fragment("d%")
+    );
+    assert_eq!(
+        Ok(Fragment::Dice(DiceFragment {
+            count: Amount::Fixed(3),
+            sides: Amount::Fixed(6),
+            kind: DieKind::Standard,
+            reroll: None,
+            explosion: Some(Explosion::Penetrating),
+            explode_on: None,
+            each: None,
+            cap: None,
+            floor: None,
+            selection: None,
+            pool: None,
+            negative: false,
+        })),
+        parse_fragment("3d6p")
+    );
+    assert_eq!(
+        Ok(Fragment::Dice(DiceFragment {
+            count: Amount::Expression("1d4".to_string()),
+            sides: Amount::Expression("(1d4)d6+2".to_string()),
+            kind: DieKind::Standard,
+            reroll: None,
+            explosion: None,
+            explode_on: None,
+            each: None,
+            cap: None,
+            floor: None,
+            selection: None,
+            pool: None,
+            negative: false,
+        })),
+        parse_fragment("(1d4)d((1d4)d6+2)")
+    );
+    assert_eq!(
+        Err(RollError::InvalidFragment { fragment: "(1d4)6".to_string(), position: 0 }),
+        parse_fragment("(1d4)6")
+    );
+    assert_eq!(
+        Ok(Fragment::Dice(DiceFragment {
+            count: Amount::Fixed(6),
+            sides: Amount::Fixed(10),
+            kind: DieKind::Standard,
+            reroll: None,
+            explosion: None,
+            explode_on: None,
+            each: None,
+            cap: None,
+            floor: None,
+            selection: None,
+            pool: Some(Pool {
+                success: Comparison::GreaterOrEqual(7),
+                failure: Some(Comparison::Equal(1)),
+                double_on_max: true,
+            }),
+            negative: false,
+        })),
+        parse_fragment("6d10>=7f1dbl")
+    );
+    assert_eq!(
+        Ok(Fragment::Dice(DiceFragment {
+            count: Amount::Fixed(4),
+            sides: Amount::Fixed(6),
+            kind: DieKind::Standard,
+            reroll: Some(Reroll { target: Comparison::Less(3), rule: RerollRule::Until }),
+            explosion: Some(Explosion::Standard),
+            explode_on: None,
+            each: None,
+            cap: None,
+            floor: None,
+            selection: Some(Selection::KeepHighest(3)),
+            pool: None,
+            negative: false,
+        })),
+        parse_fragment("4d6r<3!kh3")
+    );
+    assert_eq!(
+        Ok(Fragment::Dice(DiceFragment {
+            count: Amount::Fixed(2),
+            sides: Amount::Fixed(6),
+            kind: DieKind::Average,
+            reroll: None,
+            explosion: Some(Explosion::Standard),
+            explode_on: None,
+            each: None,
+            cap: None,
+            floor: None,
+            selection: None,
+            pool: None,
+            negative: false,
+        })),
+        parse_fragment("2da!")
+    );
+    assert_eq!(parse_fragment("2da"), parse_fragment("2d6a"));
+    match (parse_fragment("-2d4!"), parse_fragment("2d4!")) {
+        (Ok(Fragment::Dice(negative)), Ok(Fragment::Dice(dice))) => {
+            assert!(negative.negative);
+            assert_eq!(DiceFragment { negative: false, ..negative }, dice);
+        }
+        other => panic!("`-2d4!` parsed as {:?}", other),
+    }
+    assert_eq!(Ok(true), parse_fragment("-d20").map(|f| matches!(f, Fragment::Dice(DiceFragment { negative: true, .. }))));
+    assert!(parse_fragment("-6d10>=7").is_err());
+    assert!(parse_fragment("--2d4").is_err());
+    for &(input, rule) in &[("d20r1", RerollRule::Until), ("d20rh1", RerollRule::KeepNew), ("d20rk1", RerollRule::KeepHigher)] {
+        match parse_fragment(input) {
+            Ok(Fragment::Dice(dice)) => {
+                assert_eq!(Some(Reroll { target: Comparison::Equal(1), rule }), dice.reroll)
+            }
+            other => panic!("`{}` parsed as {:?}", input, other),
+        }
+    }
+    assert!(parse_fragment("d20rh").is_err());
+    assert_eq!(
+        Ok(Fragment::Dice(DiceFragment {
+            count: Amount::Fixed(1),
+            sides: Amount::Fixed(10),
+            kind: DieKind::Tens,
+            reroll: None,
+            explosion: None,
+            explode_on: None,
+            each: None,
+            cap: None,
+            floor: None,
+            selection: None,
+            pool: None,
+            negative: false,
+        })),
+        parse_fragment("d00")
+    );
+    assert_eq!(Err(RollError::InvalidFragment { fragment: "d00a".to_string(), position: 0 }), parse_fragment("d00a"));
+    assert_eq!(
+        Ok(Fragment::Dice(DiceFragment {
+            count: Amount::Fixed(2),
+            sides: Amount::Fixed(666),
+            kind: DieKind::Digits,
+            reroll: None,
+            explosion: None,
+            explode_on: None,
+            each: None,
+            cap: None,
+            floor: None,
+            selection: None,
+            pool: None,
+            negative: false,
+        })),
+        parse_fragment("2d666")
+    );
+    match parse_fragment("d(66)") {
+        Ok(Fragment::Dice(dice)) => assert_eq!(DieKind::Standard, dice.kind),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(
+        Ok(Fragment::Dice(DiceFragment {
+            count: Amount::Fixed(2),
+            sides: Amount::Fixed(20),
+            kind: DieKind::Standard,
+            reroll: None,
+            explosion: Some(Explosion::Standard),
+            explode_on: None,
+            each: None,
+            cap: Some(15),
+            floor: Some(2),
+            selection: Some(Selection::KeepHighest(1)),
+            pool: None,
+            negative: false,
+        })),
+        parse_fragment("2d20!cap15floor2kh")
+    );
+    assert_eq!(
+        Ok(Fragment::Dice(DiceFragment {
+            count: Amount::Fixed(3),
+            sides: Amount::Fixed(6),
+            kind: DieKind::Standard,
+            reroll: None,
+            explosion: Some(Explosion::Standard),
+            explode_on: None,
+            each: Some(-1),
+            cap: None,
+            floor: Some(1),
+            selection: None,
+            pool: None,
+            negative: false,
+        })),
+        parse_fragment("3d6!e-1floor1")
+    );
+    assert_eq!(
+        Err(RollError::InvalidFragment { fragment: "2d20floor2cap15".to_string(), position: 0 }),
+        parse_fragment("2d20floor2cap15")
+    );
+    assert_eq!(
+        Err(RollError::InvalidAverageDice { fragment: "3d8a".to_string(), position: 0 }),
+        parse_fragment("3d8a")
+    );
+    assert_eq!(
+        Ok(Fragment::Set(DiceSet {
+            terms: vec![parse_fragment("4d6").unwrap(), parse_fragment("1d8").unwrap(), Fragment::Constant(3)],
+            selection: Some(Selection::KeepHighest(3)),
+        })),
+        parse_fragment("{4d6,1d8,3}kh3")
+    );
+    assert_eq!(
+        Err(RollError::InvalidFragment { fragment: "{4d6,6d10>=7}".to_string(), position: 0 }),
+        parse_fragment("{4d6,6d10>=7}")
+    );
+    assert_eq!(
+        Err(RollError::InvalidFragment { fragment: "{4d6,}kh1".to_string(), position: 0 }),
+        parse_fragment("{4d6,}kh1")
+    );
+    assert_eq!(
+        Ok(Fragment::Repeat(Repeat {
+            times: 3,
+            expression: "1d6+2".to_string(),
+        })),
+        parse_fragment("3(1d6+2)")
+    );
+    assert_eq!(
+        Err(RollError::InvalidFragment { fragment: "3(1d6)kh1".to_string(), position: 0 }),
+        parse_fragment("3(1d6)kh1")
+    );
+    assert_eq!(
+        Err(RollError::InvalidFragment { fragment: "4d6kh3!".to_string(), position: 0 }),
+        parse_fragment("4d6kh3!")
+    );
+    assert_eq!(
+        Err(RollError::InvalidFragment { fragment: "6d10f1>=7".to_string(), position: 0 }),
+        parse_fragment("6d10f1>=7")
+    );
+    assert_eq!(
+        Err(RollError::InvalidFragment { fragment: "6d10dbl".to_string(), position: 0 }),
+        parse_fragment("6d10dbl")
+    );
+}
+
+#[test]
+fn parse_explosion_threshold() {
+    let dice = |input: &str| match parse_fragment(input) {
+        Ok(Fragment::Dice(dice)) => dice,
+        other => panic!("{:?}", other),
+    };
+    let threshold = dice("3d6!>=5");
+    assert_eq!(Some(Explosion::Standard), threshold.explosion);
+    assert_eq!(Some(Comparison::GreaterOrEqual(5)), threshold.explode_on);
+    assert_eq!(None, threshold.pool);
+
+    let penetrating = dice("3d6p>4");
+    assert_eq!(Some(Explosion::Penetrating), penetrating.explosion);
+    assert_eq!(Some(Comparison::Greater(4)), penetrating.explode_on);
+
+    let pool = dice("6d10!>=10>=7");
+    assert_eq!(Some(Comparison::GreaterOrEqual(10)), pool.explode_on);
+    assert_eq!(Some(Comparison::GreaterOrEqual(7)), pool.pool.map(|p| p.success));
+    assert_eq!(None, dice("3d6!").explode_on);
+}
+
+#[test]
+fn parse_conditionals() {
+    let conditional = |condition: &str, then: &str, otherwise: &str| {
+        Ok(Fragment::Conditional(Conditional {
+            condition: condition.to_string(),
+            then: then.to_string(),
+            otherwise: otherwise.to_string(),
+        }))
+    };
+    assert_eq!(conditional("1d20>=15", "2d6", "0"), parse_fragment("1d20>=15?2d6:0"));
+    assert_eq!(conditional("1d20>=15", "2d6", "0"), parse_fragment("(1d20>=15?2d6:0)"));
+    assert_eq!(conditional("1d2", "1d4?1:2", "3"), parse_fragment("1d2?1d4?1:2:3"));
+    assert_eq!(conditional("1d2", "1", "1d4?2:3"), parse_fragment("1d2?1:1d4?2:3"));
+    assert_eq!(conditional("(1d2?1:0)", "2", "3"), parse_fragment("(1d2?1:0)?2:3"));
+    for input in &["1d20?2d6", "?2d6:0", "1d20?:0", "1d20?2d6:"] {
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: input.to_string(), position: 0 }),
+            parse_fragment(input)
+        );
+    }
+}
+
+#[test]
+fn split_tags() {
+    assert_eq!(("2d6", Some("fire")), split_tag("2d6[fire]"));
+    assert_eq!(("3", Some("cold-iron")), split_tag("3[cold-iron]"));
+    assert_eq!(("{4d6,1d8}kh3", Some("x2")), split_tag("{4d6,1d8}kh3[x2]"));
+    for input in &["2d6", "2d6[4,5]", "2d6[]", "2d6[2x]", "[fire]", "2d6[fire]+1"] {
+        assert_eq!((*input, None), split_tag(input));
+    }
+    assert_eq!(parse_fragment("4d6kh3"), parse_fragment("4d6kh3[strength]"));
+    assert_eq!(
+        Err(RollError::InvalidFragment { fragment: "2dx[fire]".to_string(), position: 0 }),
+        parse_fragment("2dx[fire]")
+    );
+}
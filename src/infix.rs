@@ -1,16 +1,66 @@
 //! Provides facilities for parsing input into infix notation.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use tokens::match_token;
 
+/// The brackets written around each operation of an infix formula.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BracketStyle {
+    /// `[3 + [4 * 6]]`
+    #[default]
+    Square,
+    /// `(3 + (4 * 6))`
+    Round,
+    /// `{3 + {4 * 6}}`
+    Curly,
+    /// `3 + 4 * 6`, which leaves out how the formula is grouped.
+    None,
+}
+
+impl BracketStyle {
+    /// Returns the opening and closing brackets of the style, which are empty for `None`.
+    pub fn delimiters(&self) -> (&'static str, &'static str) {
+        match *self {
+            BracketStyle::Square => ("[", "]"),
+            BracketStyle::Round => ("(", ")"),
+            BracketStyle::Curly => ("{", "}"),
+            BracketStyle::None => ("", ""),
+        }
+    }
+}
+
 /// Returns a Vector of Strings with each element containing a token or an operator in bracketed infix format.
 ///
 /// # Arguments
 /// * `input_formula` - A Vector of Strings that provides the postfix formatted notation to work off.
-/// See [rfyl::parse_into_rpn()](fn.parse_into_rpn.html) for more details.
+///   See [rfyl::parse_into_rpn()](fn.parse_into_rpn.html) for more details.
 ///
 /// # Example values
 ///
 /// * `["3", "4", "6", "*", "+"]` -> `["(", "3", "+", "(", "4", "*", "6", ")", ")"]`
 pub fn parse_into_infix(input_formula: Vec<String>) -> String {
+    render_infix(&input_formula, "( ", " )")
+}
+
+/// Returns a postfix formula as an infix formatted String, with each operation bracketed
+/// in the given style.
+///
+/// # Arguments
+/// * `input_formula` - A slice of Strings that provides the postfix formatted notation to work off.
+/// * `style` - The brackets written around each operation.
+///
+/// # Example values
+///
+/// * `["3", "4", "6", "*", "+"]`, `BracketStyle::Square` -> `[3 + [4 * 6]]`
+/// * `["3", "4", "6", "*", "+"]`, `BracketStyle::None` -> `3 + 4 * 6`
+pub fn parse_into_infix_with(input_formula: &[String], style: BracketStyle) -> String {
+    let (open, close) = style.delimiters();
+    render_infix(input_formula, open, close)
+}
+
+/// Returns a postfix formula in infix format, writing `open` and `close` around each
+/// operation as it is read, so that values are never mistaken for brackets.
+fn render_infix(input_formula: &[String], open: &str, close: &str) -> String {
     let mut formula_vector: Vec<String> = Vec::new();
     let mut formula_string = String::new();
 
@@ -24,7 +74,7 @@ pub fn parse_into_infix(input_formula: Vec<String>) -> String {
             } else {
                 if let Some(a) = formula_vector.pop() {
                     if let Some(b) = formula_vector.pop() {
-                        formula_vector.push(format!("( {0} {1} {2} )", b, e, a));
+                        formula_vector.push(format!("{0}{1} {2} {3}{4}", open, b, e, a, close));
                     } else {
                         panic!("Right hand token in evaluation doesn't exist");
                     }
@@ -34,7 +84,7 @@ pub fn parse_into_infix(input_formula: Vec<String>) -> String {
             },
             // Non-operator
             _ => {
-                formula_vector.push(e);
+                formula_vector.push(e.to_string());
             }
         }
     }
@@ -43,11 +93,11 @@ pub fn parse_into_infix(input_formula: Vec<String>) -> String {
         formula_string = formula_vector[0].to_string();
     } else if formula_vector.len() > 1 {
         panic!("Too many values in postfix formula. Please verify the formula.");
-    } else if formula_vector.len() < 1 {
+    } else if formula_vector.is_empty() {
         panic!("Not enough values in postfix formula. Please verify the formula.");
     }
 
-    return formula_string;
+    formula_string
 }
 
 #[test]
@@ -80,4 +130,21 @@ fn parse_infix_formula() {
             "+".to_string(),
         ])
     );
-}
\ No newline at end of file
+}
+#[test]
+fn parse_nested_infix_formula() {
+    let formula = |terms: &[&str]| terms.iter().map(|t| t.to_string()).collect::<Vec<String>>();
+
+    // ((1 + 2) * (3 - (4 / 5))) + 6
+    let nested = formula(&["1", "2", "+", "3", "4", "5", "/", "-", "*", "6", "+"]);
+    assert_eq!("[[[1 + 2] * [3 - [4 / 5]]] + 6]", parse_into_infix_with(&nested, BracketStyle::Square));
+    assert_eq!("(((1 + 2) * (3 - (4 / 5))) + 6)", parse_into_infix_with(&nested, BracketStyle::Round));
+    assert_eq!("{{{1 + 2} * {3 - {4 / 5}}} + 6}", parse_into_infix_with(&nested, BracketStyle::Curly));
+    assert_eq!("1 + 2 * 3 - 4 / 5 + 6", parse_into_infix_with(&nested, BracketStyle::None));
+    assert_eq!("( ( ( 1 + 2 ) * ( 3 - ( 4 / 5 ) ) ) + 6 )", parse_into_infix(nested));
+
+    // Brackets within a value are left as they are.
+    let bracketed = formula(&["(1d4)d6", "2d(1d4 )", "( 3", "-", "+"]);
+    assert_eq!("[(1d4)d6 + [2d(1d4 ) - ( 3]]", parse_into_infix_with(&bracketed, BracketStyle::Square));
+    assert_eq!("2d6", parse_into_infix_with(&formula(&["2d6"]), BracketStyle::Square));
+}
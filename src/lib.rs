@@ -1,15 +1,37 @@
 //! RFYL implements the common dice notation used in many role playing game systems.
 //! 
 //! ## Supported input
-//! - Basic standard dice notation: `d8`, `2d12`.
+//! - Basic standard dice notation: `d8`, `2d12`. An uppercase `D` works the same, as in `D20` or `2D6`.
+//! - Default sides: `d` and `3d` roll `1d6` and `3d6` when `default_sides` is set to `6` in the `RollOptions` given to `roll_with_options()`. Without it, dice need their sides.
 //! - Addition: `d4 + 2d6`.
 //! - Subtraction: `d100 - 15`.
-//! - Multiplication: `d12 * 2`.
-//! - Division: `d100 / 15`. (Note that fractional values are rounded to the nearest integer.)
+//! - Multiplication: `d12 * 2`, `d12 × 2` or `d12 x 2`.
+//! - Division: `d100 / 15`. (Note that fractional values are rounded to the nearest integer, unless another `RoundingMode` is given to `roll_with_options()`.)
 //! - Brackets: `(d100 + d12) / 15`.
 //! - Complex dice notation: `1d4 + 2d6 * 3d2 / 4d8 + (2d6 + 3d8) - 16 * (1 / 1d4)`.
-//! - Percentile dice shorthand: `d%` = `d100`.
-//! - Boolean dice: `1d1` = `0` or `1`.
+//! - Percentile dice shorthand: `d%` = `d100`. (With `percentile_breakdown` turned on in the `RollOptions` given to `roll_with_options()`, `d%` is rolled as a tens die and a ones die, shown as `70 + 3 = 73`.)
+//! - Percentile tens dice: `d00` rolls one of `0, 10, ..., 90`, so `d00 + d10` rolls from `1` to `100`. (As a `d10` rolls from `1` to `10`, there is no `00` and `0` to read as `100`.)
+//! - Digit dice: `d66` rolls two d6s and reads them as tens and ones, giving `11` to `66`, and `d666` does the same with three. (Bracket the sides, as in `d(66)`, for a die with 66 sides.)
+//! - Grouped thousands: `1,000 + 2d6`. Digits may be grouped in threes by commas or thin spaces.
+//! - Rolled counts and sides: `(1d4)d6` rolls one to four `d6`, and `2d(1d4 + 2)` rolls two dice with three to six sides. A count of zero or less rolls no dice, but dice need at least one side.
+//! - Repeats: `3(1d6 + 2)` rolls `1d6 + 2` three times and adds up the results, unlike `3 * (1d6 + 2)`, which rolls it once. A negative repeat, as in `-2(1d6)`, takes the total away instead, and a repeat of zero counts as `0`.
+//! - Boolean dice: `1d1` = `0` or `1`. (Unless `boolean_dice` is turned off in the `RollOptions` given to `roll_with_options()`, in which case `1d1` = `1`.)
+//! - Average dice: `3da` (or `3d6a`) rolls six-sided dice with the faces `2, 3, 3, 4, 4, 5`, which explode on a `5`.
+//! - Rerolls: `4d6r1` rerolls any `1` until it shows something else, and `4d6r<3` does the same for anything under `3`. `d20rh1` rerolls a `1` once and keeps the new roll, as with halfling luck, and `d20rk1` rerolls a `1` once and keeps the higher roll.
+//! - Exploding dice: `3d6!` rolls again and adds on a `6`. A threshold can follow, as in `3d6!>=5`, to explode on a `5` or `6`, so a pool of exploding dice is written with both, as in `6d10!>=10>=7`.
+//! - Penetrating dice: `3d6p` rolls again on a `6`, counting each additional die as one less than it shows. These can take a threshold too, as in `3d6p>=5`.
+//! - Capped and floored dice: `2d20cap15` counts any roll over `15` as `15`, and `4d6floor2` counts any roll under `2` as `2`. Dice still explode on the face they show.
+//! - Per-die modifiers: `3d6e1` adds `1` to each die rather than to the sum, and `3d6e-1` takes `1` from each die.
+//! - Keeping and dropping: `4d6kh3` keeps the highest three dice, `2d20kl1` the lowest, and `4d6dl1` and `4d6dh1` drop the lowest or highest.
+//! - Sets: `{4d6, 1d8}kh3` rolls all five dice and keeps the highest three, whatever their sides. Any of the keep or drop modifiers can follow the braces.
+//! - Conditionals: `1d20>=15 ? 2d6 : 0` rolls `2d6` if the `d20` counts a success, and otherwise counts `0`, without rolling the `2d6`. A condition without a dice pool is met by any result other than `0`. A conditional takes in the whole formula, so bracket it to add to it, as in `(1d20>=15 ? 2d6 : 0) + 3`.
+//! - Negative dice: `2d6 + -2d4` counts each of the `d4`s against the total, showing them as `d4 -> [-3]`. A `-` makes dice negative where a value is expected, as at the start of a formula or after another operator, and subtracts them as usual between two terms. Negative dice can't count successes.
+//! - Tags: `2d6[fire] + 1d6[cold] + 3` tags each term, and `get_result_by_tag("fire")` gives the sum of the terms tagged `fire`, taking away any the formula subtracts. (Read with `get_results_by_tag()` for every tag at once, with terms written without one under `Untagged`.) A tag starts with a letter and has only letters, digits and `-` in it.
+//! - Dice modifiers apply in the order they are written, which must be: rerolls, then explosions, then per-die modifiers, caps and floors, then keeping or dropping, then dice pools, as in `4d6r1!cap5kh3>=4`.
+//! - Difficulty classes: `1d20 + 5 dc15` (or `vs15`) at the end of a formula checks whether the result meets the target. (Read with `succeeded()`.)
+//! - Critical results: `1d20 + 5 dc15 crit25` makes a result of `25` or more a critical success, and `fumble<n>` makes a result of `n` or less a critical failure. (Read with `get_success_tier()`, which orders `CritFail`, `Fail`, `Success` and `CritSuccess`. Without a difficulty class, only critical results have a tier.)
+//! - Halving: `2d6 + 3 half` at the end of a formula halves the result, rounding down, and `2d6 + 3 halfup` halves it rounding up. Either can come before or after a difficulty class.
+//! - Dice pools: `6d10>=7` counts the dice meeting the target as successes, `6d10>=7f1` subtracts a success for each `1`, and `6d10>=7dbl` counts each maximum roll as two successes. (Read with `get_successes()`, or `get_pool_result()` for the successes and failures separately.) (With `count_pool_successes` turned on in the `RollOptions` given to `roll_with_options()`, each pool counts for its successes in the formula rather than the sum of its dice, so `4d6>=5 + 2` adds `2` to the successes.)
 //! 
 //! ## Example
 //! 
@@ -19,7 +41,7 @@
 //! // This would actually probably come from user input, or be computed in some other way.
 //! let requested_roll = String::from("(1d20 * 2) + (1d4 + 1) / 2");
 //! 
-//! // Rolling can fail, for instance if an illegal digit is supplied.
+//! // Rolling can fail, for instance if an illegal digit is supplied or the input is empty.
 //! // Therefore, roll() returns a Result which must be unwrapped.
 //! let roll = roll(requested_roll).unwrap();
 //! 
@@ -30,17 +52,62 @@
 //! ```
 //! 
 //! See the included command line program ([src/bin.rs](https://github.com/trnglina/RFYL/blob/master/src/bin.rs)) for further examples of how to use the [DiceRolls](struct.DiceRolls.html) struct.
+//! 
+//! ## Without the standard library
+//! 
+//! Everything that rolls with `thread_rng()`, including [roll()](fn.roll.html), is part of the
+//! default `std` feature. Without it, the crate is `no_std` (though it still needs `alloc`),
+//! and dice are rolled with a random number generator given to [roll_with_rng()](fn.roll_with_rng.html)
+//! or [evaluate_with_rng()](fn.evaluate_with_rng.html).
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg_attr(not(feature = "std"), macro_use)]
+extern crate alloc;
 extern crate rand;
-use self::rand::{thread_rng, Rng};
+#[cfg(feature = "std")]
+use self::rand::rngs::StdRng;
+#[cfg(feature = "std")]
+use self::rand::{thread_rng, SeedableRng};
+use self::rand::Rng;
+use alloc::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+use core::cmp::Ordering;
+#[cfg(feature = "std")]
+use std::cmp::Ordering;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
 pub mod rpn;
 pub mod infix;
+mod error;
+mod expression;
+mod fragment;
+mod options;
 mod tokens;
 
-use tokens::match_token;
-use rpn::{parse_into_rpn};
-use infix::{parse_into_infix};
+pub use error::RollError;
+pub use expression::{parse_expression, parse_tokens, ExpressionBuilder, ParsedExpression};
+#[cfg(feature = "std")]
+use expression::split_modifiers;
+pub use options::{AggregateMode, RollOptions, RoundingMode};
+pub use tokens::{Operator, RpnToken};
+use fragment::{
+    parse_fragment, split_tag, Amount, DiceFragment, DieKind, Explosion, Fragment, Reroll, RerollRule, Selection, AVERAGE_DIE_FACES,
+};
+use tokens::{find_closing_bracket, find_top_level, match_token};
+#[cfg(feature = "std")]
+use tokens::{split_top_level, tokenize};
+use rpn::validate_rpn_formula;
+use infix::parse_into_infix_with;
+pub use infix::BracketStyle;
+
+/// The separator between formulas suggested for [roll_multi()](fn.roll_multi.html), as it
+/// has no other meaning in dice notation.
+pub const DEFAULT_MULTI_SEPARATOR: char = ';';
+
+/// The most times a single die may be rerolled.
+const MAX_REROLL_DEPTH: i32 = 100;
 
 /// The result of rolling some dice.
 #[derive(Clone)]
@@ -48,35 +115,952 @@ pub struct DiceRolls {
     rolls: Vec<DiceRoll>,
     formula: Vec<String>,
     rolls_formula: Vec<String>,
+    pool: Option<PoolResult>,
+    dc: Option<i32>,
+    half: Option<RoundingMode>,
+    crit: Option<i32>,
+    fumble: Option<i32>,
+    /// Every face rolled, in the order the dice were rolled, for replaying the roll.
+    faces: Vec<i32>,
+    groups: Vec<GroupSpan>,
+    options: RollOptions,
 }
 
 impl DiceRolls {
+    /// Returns a DiceRolls object built from dice that have already been rolled, without
+    /// any randomness. This allows the formatting methods to be reused for rolls made
+    /// elsewhere, or with known values in tests.
+    ///
+    /// Both formulas must be well-formed postfix (rpn) formulas with their operators in the
+    /// same places, and every value in `formula` must be a number.
+    ///
+    /// # Arguments
+    /// * `rolls` - The individual dice, with constant terms given `sides` of `0`.
+    /// * `formula` - The postfix formula with each term replaced by its value, e.g. `["9", "3", "+"]`.
+    /// * `rolls_formula` - The postfix formula in its original notation, e.g. `["2d6", "3", "+"]`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::{DiceRolls, RollResult};
+    ///
+    /// let rolls = DiceRolls::from_parts(
+    ///     vec![
+    ///         RollResult { sides: 6, result: 4 },
+    ///         RollResult { sides: 6, result: 5 },
+    ///         RollResult { sides: 0, result: 3 },
+    ///     ],
+    ///     vec!["9".to_string(), "3".to_string(), "+".to_string()],
+    ///     vec!["2d6".to_string(), "3".to_string(), "+".to_string()],
+    /// ).unwrap();
+    /// assert_eq!(12, rolls.get_result());
+    /// ```
+    pub fn from_parts(
+        rolls: Vec<RollResult>,
+        formula: Vec<String>,
+        rolls_formula: Vec<String>,
+    ) -> Result<DiceRolls, RollError> {
+        validate_rpn_formula(&formula)?;
+        validate_rpn_formula(&rolls_formula)?;
+        if formula.len() != rolls_formula.len() {
+            return Err(RollError::MismatchedFormulas);
+        }
+        for (index, (value, notation)) in formula.iter().zip(rolls_formula.iter()).enumerate() {
+            if match_token(value) > 0 {
+                if value != notation {
+                    return Err(RollError::MismatchedFormulas);
+                }
+            } else if match_token(notation) > 0 {
+                return Err(RollError::MismatchedFormulas);
+            } else if value.parse::<i32>().is_err() {
+                return Err(RollError::InvalidFragment {
+                    fragment: value.to_string(),
+                    position: index,
+                });
+            }
+        }
+
+        Ok(DiceRolls {
+            faces: rolls.iter().filter(|r| r.sides != 0).map(|r| r.result).collect(),
+            rolls: rolls
+                .into_iter()
+                .map(|r| DiceRoll {
+                    sides: r.sides,
+                    highest: r.sides,
+                    result: r.result,
+                    natural: r.result,
+                    kept: true,
+                    percentile: false,
+                    negative: false,
+                })
+                .collect(),
+            formula,
+            rolls_formula,
+            pool: None,
+            dc: None,
+            half: None,
+            crit: None,
+            fumble: None,
+            groups: Vec::new(),
+            options: RollOptions::default(),
+        })
+    }
+
+    /// Returns a DiceRolls object rebuilt from a string given by
+    /// [to_replay_string()](#method.to_replay_string), with the same dice and result, without
+    /// any randomness. The formula is rolled again with the default
+    /// [RollOptions](struct.RollOptions.html), taking each die's face from those written.
+    ///
+    /// A string without the list of faces returns `RollError::InvalidFragment`, as does a
+    /// face that isn't a number, and faces that don't match the dice of the formula, by being
+    /// too few or too many, return `RollError::MismatchedFormulas`. A face its die couldn't
+    /// have shown, such as `7` for a `d6`, returns `RollError::ImpossibleFace`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::{roll, DiceRolls};
+    ///
+    /// let rolls = roll("4d6kh3 + 2".to_string()).unwrap();
+    /// let replayed = DiceRolls::from_replay_string(&rolls.to_replay_string()).unwrap();
+    /// assert_eq!(rolls.get_result(), replayed.get_result());
+    /// assert_eq!(rolls.get_rolls_string(), replayed.get_rolls_string());
+    /// ```
+    pub fn from_replay_string(input: &str) -> Result<DiceRolls, RollError> {
+        let invalid = |fragment: &str, position: usize| RollError::InvalidFragment {
+            fragment: fragment.to_string(),
+            position,
+        };
+        let start = match input.rfind("= [") {
+            Some(start) if input.trim_end().ends_with(']') => start,
+            _ => return Err(invalid(input, 0)),
+        };
+        let list = input[start + 3..].trim_end();
+        let list = &list[..list.len() - 1];
+        let mut faces: Vec<(i32, usize)> = Vec::new();
+        if !list.trim().is_empty() {
+            let mut offset = input[..start + 3].chars().count();
+            for face in list.split(',') {
+                let position = offset + face.chars().take_while(|c| c.is_whitespace()).count();
+                faces.push((face.trim().parse().map_err(|_| invalid(face.trim(), position))?, position));
+                offset += face.chars().count() + 1;
+            }
+        }
+
+        let expression = parse_expression(&input[..start])?;
+        let mut replay = ReplayFaces {
+            faces: faces.iter(),
+            exhausted: false,
+            impossible: None,
+        };
+        let rolls = evaluate_with_roller(&expression, &RollOptions::default(), &mut replay)?;
+        if let Some((face, position)) = replay.impossible {
+            return Err(RollError::ImpossibleFace { face, position });
+        }
+        if replay.exhausted || replay.faces.next().is_some() {
+            return Err(RollError::MismatchedFormulas);
+        }
+        Ok(rolls)
+    }
+
+    /// Returns a DiceRolls object joining the given rolls together with `operator`, as if
+    /// they had been rolled as one formula. The operator applies to everything before it,
+    /// so the parts are joined from left to right, as in `(a - b) - c`.
+    ///
+    /// The dice of every part are kept in order, and the combined roll uses the options of
+    /// the first part. A difficulty class is only kept if every part has the same one.
+    /// Each part counts for its own result, so a part that was halved or raised to its
+    /// `floor_result_at` option is joined as that result, in a single term written as the
+    /// part was, and the combined roll is neither halved nor raised again.
+    /// Combining no rolls gives a roll with an empty formula, whose
+    /// [get_result_checked()](#method.get_result_checked) returns `RollError::EmptyExpression`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::{roll, DiceRolls, Operator};
+    ///
+    /// let weapon = roll("1d8 + 3".to_string()).unwrap();
+    /// let sneak_attack = roll("2d6".to_string()).unwrap();
+    /// let damage = DiceRolls::combine(&[weapon.clone(), sneak_attack.clone()], Operator::Add);
+    /// assert_eq!(weapon.get_result() + sneak_attack.get_result(), damage.get_result());
+    /// ```
+    pub fn combine(others: &[DiceRolls], operator: Operator) -> DiceRolls {
+        let mut combined = DiceRolls {
+            rolls: Vec::new(),
+            formula: Vec::new(),
+            rolls_formula: Vec::new(),
+            pool: None,
+            dc: others.first().and_then(|first| first.dc),
+            half: None,
+            crit: others.first().and_then(|first| first.crit),
+            fumble: others.first().and_then(|first| first.fumble),
+            faces: Vec::new(),
+            groups: Vec::new(),
+            options: RollOptions {
+                floor_result_at: None,
+                ..others.first().map(|first| first.options.clone()).unwrap_or_default()
+            },
+        };
+
+        for (i, part) in others.iter().enumerate() {
+            combined.rolls.extend(part.rolls.iter().cloned());
+            combined.faces.extend(part.faces.iter().cloned());
+            let adjusted = part.half.is_some() || part.options.floor_result_at.is_some();
+            match part.get_result_checked() {
+                Ok(result) if adjusted => {
+                    combined.formula.push(result.to_string());
+                    combined.rolls_formula.push(result.to_string());
+                    combined.groups.push(GroupSpan {
+                        notation: write_notation(&part.rolls_formula, part.half, None, None, None),
+                        len: part.rolls.len(),
+                        subtotal: result,
+                        tag: None,
+                    });
+                }
+                _ => {
+                    combined.formula.extend(part.formula.iter().cloned());
+                    combined.rolls_formula.extend(part.rolls_formula.iter().cloned());
+                    combined.groups.extend(part.groups.iter().cloned());
+                }
+            }
+            if i > 0 {
+                combined.formula.push(operator.symbol().to_string());
+                combined.rolls_formula.push(operator.symbol().to_string());
+            }
+            if let Some(part_pool) = part.pool {
+                combined.pool = Some(combined.pool.unwrap_or_default().combine(part_pool));
+            }
+            if part.dc != combined.dc {
+                combined.dc = None;
+            }
+            if part.crit != combined.crit {
+                combined.crit = None;
+            }
+            if part.fumble != combined.fumble {
+                combined.fumble = None;
+            }
+        }
+        combined
+    }
+
     /// Returns an i32 as the result of the formula including any calculational
-    /// operators.
+    /// operators, with divisions rounded as requested when rolling.
+    ///
+    /// This panics where [get_result_checked()](#method.get_result_checked) returns an
+    /// error, such as when the formula divides by zero.
     pub fn get_result(&self) -> i32 {
-        return rpn::solve_rpn_formula(self.formula.clone());
+        match self.get_result_checked() {
+            Ok(result) => result,
+            Err(error) => panic!("Cannot solve `{}`: {}", self.formula.join(" "), error),
+        }
+    }
+
+    /// Compares the result of this roll with the result of another, as when settling an
+    /// opposed roll or sorting initiative. A roll whose result can't be solved, such as one
+    /// that divides by zero, is lower than any that can.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::roll;
+    ///
+    /// let mut initiative = vec![roll("5".to_string()).unwrap(), roll("12".to_string()).unwrap()];
+    /// initiative.sort_by(|a, b| b.compare(a));
+    /// assert_eq!(12, initiative[0].get_result());
+    /// ```
+    pub fn compare(&self, other: &DiceRolls) -> Ordering {
+        self.get_result_checked().ok().cmp(&other.get_result_checked().ok())
+    }
+
+    /// Returns the result of the roll, or an error instead of panicking if it can't be
+    /// solved. See [rpn::solve_rpn_formula_with_options()](rpn/fn.solve_rpn_formula_with_options.html).
+    ///
+    /// The result is halved if the formula ended with `half` or `halfup`, and is then no
+    /// lower than the `floor_result_at` option the roll was made with.
+    pub fn get_result_checked(&self) -> Result<i32, RollError> {
+        Ok(self.adjust_total(self.raw_total()?))
+    }
+
+    /// Returns the total of the formula, before any halving, or raising to the
+    /// `floor_result_at` option.
+    fn raw_total(&self) -> Result<i32, RollError> {
+        rpn::solve_rpn_formula_with_options(&self.formula, &self.options)
+    }
+
+    /// Halves the total of the formula for `half` or `halfup`, and raises it to the
+    /// `floor_result_at` option, giving the result.
+    fn adjust_total(&self, total: i32) -> i32 {
+        let result = match self.half {
+            Some(rounding) => rounding.divide(total, 2),
+            None => total,
+        };
+        match self.options.floor_result_at {
+            Some(min) => result.max(min),
+            None => result,
+        }
+    }
+
+    /// Returns the probability, from `0` to `1`, of rolling exactly the result that was
+    /// rolled, e.g. `1 / 36` for a `12` on `2d6`.
+    ///
+    /// This is only worked out for formulas that add and subtract constants and dice without
+    /// any dice modifiers, such as `2d6 + 1d4 - 1`. Other formulas, including dice pools and
+    /// formulas with so many dice that the chances would take too long to work out, give None.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::roll;
+    ///
+    /// let single = roll("1d20 + 5".to_string()).unwrap();
+    /// assert_eq!(Some(0.05), single.get_result_probability());
+    /// assert_eq!(None, roll("1d20 * 2".to_string()).unwrap().get_result_probability());
+    /// ```
+    pub fn get_result_probability(&self) -> Option<f64> {
+        if self.pool.is_some() {
+            return None;
+        }
+        let result = self.get_result_checked().ok()?;
+        let distribution = total_distribution(&self.rolls_formula, &self.options)?;
+        Some(
+            distribution
+                .iter()
+                .filter(|&(&total, _)| self.adjust_total(total) == result)
+                .map(|(_, p)| p)
+                .sum(),
+        )
+    }
+
+    /// Returns how many standard deviations the total of the formula is from its mean, as
+    /// a measure of luck that can be compared across different dice. A `12` on `2d6` is about
+    /// `2.07`, an unusually high roll, and a negative number shows a low one.
+    ///
+    /// As with [get_result_probability()](#method.get_result_probability), this is only
+    /// worked out for formulas that add and subtract constants and dice without any dice
+    /// modifiers, and other formulas give None, as do formulas made up only of constants.
+    /// The total is taken before any halving, or raising to the `floor_result_at` option.
+    pub fn get_deviation(&self) -> Option<f64> {
+        if self.pool.is_some() {
+            return None;
+        }
+        let total = self.raw_total().ok()?;
+        let (mean, variance) = total_moments(&self.rolls_formula, &self.options)?;
+        if variance <= 0.0 {
+            return None;
+        }
+        Some((f64::from(total) - mean) / sqrt(variance))
     }
 
-    /// Returns an i32 as the simple sum of all rolls.
+    /// Returns an i32 as the simple sum of all rolls, leaving out any dice that were
+    /// dropped or rerolled.
     pub fn get_sum_of_rolls(&self) -> i32 {
         let mut total = 0;
-        for roll in &self.rolls {
+        for roll in self.rolls.iter().filter(|r| r.kept) {
             total += roll.result;
         }
-        return total;
+        total
+    }
+
+    /// Returns a new DiceRolls object from rolling the same formula again, with the same
+    /// options, without reading the original input again. This DiceRolls is left untouched.
+    ///
+    /// The new dice are always freshly random, even if the original roll was seeded.
+    #[cfg(feature = "std")]
+    pub fn reroll(&self) -> Result<DiceRolls, RollError> {
+        let mut rng = thread_rng();
+        let mut limited = OperationLimit::new(&mut rng, self.options.max_operations);
+        let mut recorded = RecordFaces::new(&mut limited);
+        let rolls = resolve_rolls_vector(self.rolls_formula.clone(), &[], &self.options, &mut recorded)?;
+        Ok(DiceRolls {
+            dc: self.dc,
+            half: self.half,
+            crit: self.crit,
+            fumble: self.fumble,
+            faces: recorded.faces,
+            ..rolls
+        })
+    }
+
+    /// Returns the difficulty class given with `dc<n>` or `vs<n>` at the end of the
+    /// formula, or None if there wasn't one.
+    pub fn get_dc(&self) -> Option<i32> {
+        self.dc
+    }
+
+    /// Returns whether the result met the difficulty class given with `dc<n>` or `vs<n>`
+    /// at the end of the formula, or None if there wasn't one or the result can't be solved.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::roll;
+    ///
+    /// assert_eq!(Some(true), roll("10 + 5 dc15".to_string()).unwrap().succeeded());
+    /// assert_eq!(Some(false), roll("10 + 4 dc15".to_string()).unwrap().succeeded());
+    /// assert_eq!(None, roll("10 + 5".to_string()).unwrap().succeeded());
+    /// ```
+    pub fn succeeded(&self) -> Option<bool> {
+        let dc = self.dc?;
+        self.get_result_checked().ok().map(|result| result >= dc)
+    }
+
+    /// Returns how well the roll succeeded, by the difficulty class and the `crit<n>` and
+    /// `fumble<n>` thresholds given at the end of the formula, as in `1d20 + 5 dc15 crit25`.
+    ///
+    /// A result at or above the `crit` threshold is a critical success, and one at or below
+    /// the `fumble` threshold is a critical failure, with the critical success checked first.
+    /// Any other result is a success or failure by the difficulty class. Without a difficulty
+    /// class, only critical results have a tier, and other results give None, as does a
+    /// formula whose result can't be solved.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::{roll, SuccessTier};
+    ///
+    /// assert_eq!(Some(SuccessTier::CritSuccess), roll("20 + 5 dc15 crit25".to_string()).unwrap().get_success_tier());
+    /// assert_eq!(Some(SuccessTier::Success), roll("12 + 5 dc15 crit25".to_string()).unwrap().get_success_tier());
+    /// assert_eq!(None, roll("12 + 5 crit25".to_string()).unwrap().get_success_tier());
+    /// ```
+    pub fn get_success_tier(&self) -> Option<SuccessTier> {
+        let result = self.get_result_checked().ok()?;
+        if self.crit.map_or(false, |crit| result >= crit) {
+            Some(SuccessTier::CritSuccess)
+        } else if self.fumble.map_or(false, |fumble| result <= fumble) {
+            Some(SuccessTier::CritFail)
+        } else {
+            self.dc.map(|dc| if result >= dc { SuccessTier::Success } else { SuccessTier::Fail })
+        }
+    }
+
+    /// Returns the outcome of a d20 roll under the common rule that a natural `1` misses and
+    /// a natural `20` hits, whatever is added to the roll, or the result for any other face.
+    ///
+    /// The formula must have a single d20 that counts towards the total. Dice that were
+    /// dropped or rerolled are left out, so `2d20kh1 + 5` has a single d20. A formula
+    /// with no d20 or more than one gives None, as does a formula whose result can't be
+    /// solved when the d20 shows neither a `1` nor a `20`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::D20Outcome;
+    ///
+    /// let rolls = rfyl::roll("1d20 + 5".to_string()).unwrap();
+    /// match rolls.get_d20_outcome() {
+    ///     Some(D20Outcome::AutoFail) => assert_eq!(6, rolls.get_result()),
+    ///     Some(D20Outcome::AutoSuccess) => assert_eq!(25, rolls.get_result()),
+    ///     Some(D20Outcome::Normal(total)) => assert_eq!(rolls.get_result(), total),
+    ///     None => unreachable!(),
+    /// }
+    /// assert_eq!(None, rfyl::roll("2d20 + 5".to_string()).unwrap().get_d20_outcome());
+    /// ```
+    pub fn get_d20_outcome(&self) -> Option<D20Outcome> {
+        let mut d20s = self.rolls.iter().filter(|r| r.kept && r.sides == 20 && !r.percentile);
+        let d20 = d20s.next()?;
+        if d20s.next().is_some() {
+            return None;
+        }
+        match d20.natural {
+            1 => Some(D20Outcome::AutoFail),
+            20 => Some(D20Outcome::AutoSuccess),
+            _ => self.get_result_checked().ok().map(D20Outcome::Normal),
+        }
+    }
+
+    /// Returns the net number of successes rolled by any dice pools in the formula,
+    /// such as `6d10>=7f1`, or None if the formula contains no dice pools.
+    ///
+    /// Each die meeting the success target counts as one success (two with `dbl` on a
+    /// maximum roll), and each die meeting the failure target subtracts one.
+    pub fn get_successes(&self) -> Option<i32> {
+        self.pool.map(|pool| pool.net)
+    }
+
+    /// Returns the successes and failures rolled by any dice pools in the formula, or
+    /// None if the formula contains no dice pools.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::roll;
+    ///
+    /// let pool = roll("6d10>=7f1".to_string()).unwrap().get_pool_result().unwrap();
+    /// assert_eq!(pool.successes - pool.failures, pool.net);
+    /// assert_eq!(None, roll("6d10".to_string()).unwrap().get_pool_result());
+    /// ```
+    pub fn get_pool_result(&self) -> Option<PoolResult> {
+        self.pool
+    }
+
+    /// Returns an iterator over the individual dice rolled, in the order they were rolled.
+    /// Constant terms are included with `sides` set to `0`.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            inner: self.rolls.iter(),
+        }
+    }
+
+    /// Returns the dice rolled for each term of the formula, in the order the terms were
+    /// written, along with each term's notation and subtotal. Dice that were dropped or
+    /// rerolled are included, as are any dice rolled for a bracketed count or sides.
+    ///
+    /// A DiceRolls object built with [from_parts()](#method.from_parts) has no groups.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let roll = rfyl::roll("2d6 + 3d8".to_string()).unwrap();
+    /// let groups = roll.get_groups();
+    /// assert_eq!("2d6", groups[0].notation);
+    /// assert_eq!(3, groups[1].rolls.len());
+    /// assert_eq!(roll.get_result(), groups[0].subtotal + groups[1].subtotal);
+    /// ```
+    pub fn get_groups(&self) -> Vec<DiceGroup> {
+        let mut start = 0;
+        self.groups
+            .iter()
+            .map(|group| {
+                let rolls = self.rolls[start..start + group.len].iter().map(|r| r.to_result()).collect();
+                start += group.len;
+                DiceGroup {
+                    notation: group.notation.clone(),
+                    rolls,
+                    subtotal: group.subtotal,
+                    tag: group.tag.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the sum of the terms written with each tag, as in `2d6[fire] + 1d6[cold] + 3`,
+    /// with the terms written without one under `DiceTag::Untagged`. Each term counts for its
+    /// subtotal, taken away where the formula subtracts it, but before any multiplication or
+    /// division, so the results only add up to the total for formulas that just add and
+    /// subtract their terms.
+    ///
+    /// A DiceRolls object built with [from_parts()](#method.from_parts) has no groups, and
+    /// so no tags.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::DiceTag;
+    ///
+    /// let roll = rfyl::roll("2d6[fire] + 1d6[cold] + 3".to_string()).unwrap();
+    /// let results = roll.get_results_by_tag();
+    /// assert_eq!(Some(&3), results.get(&DiceTag::Untagged));
+    /// assert_eq!(roll.get_result(), results.values().sum::<i32>());
+    /// ```
+    pub fn get_results_by_tag(&self) -> BTreeMap<DiceTag, i32> {
+        let mut results = BTreeMap::new();
+        let values = self.rolls_formula.iter().filter(|e| match_token(e) <= 0).count();
+        if self.groups.len() != values {
+            return results;
+        }
+
+        // Work out which terms are taken away, following the subtractions of the formula.
+        let mut stack: Vec<Vec<usize>> = Vec::new();
+        let mut subtracted = vec![false; values];
+        let mut term = 0;
+        for element in &self.rolls_formula {
+            let precedence = match_token(element);
+            if precedence > 0 {
+                let right = stack.pop().unwrap_or_default();
+                let mut left = stack.pop().unwrap_or_default();
+                if precedence == 1 {
+                    for &index in &right {
+                        subtracted[index] = !subtracted[index];
+                    }
+                }
+                left.extend(right);
+                stack.push(left);
+            } else {
+                stack.push(vec![term]);
+                term += 1;
+            }
+        }
+
+        for (group, subtracted) in self.groups.iter().zip(subtracted) {
+            let tag = match group.tag {
+                Some(ref tag) => DiceTag::Tagged(tag.clone()),
+                None => DiceTag::Untagged,
+            };
+            let subtotal = if subtracted { -group.subtotal } else { group.subtotal };
+            let result = results.entry(tag).or_insert(0);
+            *result = result.saturating_add(subtotal);
+        }
+        results
+    }
+
+    /// Returns the sum of the terms written with the given tag, or `0` if there are none.
+    /// See [get_results_by_tag()](#method.get_results_by_tag).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let roll = rfyl::roll("2d6[fire] + 1d6[cold] + 1d4[fire]".to_string()).unwrap();
+    /// let fire = roll.get_result_by_tag("fire");
+    /// assert!(fire >= 3 && fire <= 16);
+    /// assert_eq!(roll.get_result(), fire + roll.get_result_by_tag("cold"));
+    /// ```
+    pub fn get_result_by_tag(&self, tag: &str) -> i32 {
+        self.get_results_by_tag()
+            .get(&DiceTag::Tagged(tag.to_string()))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// Returns the dice and constant terms that count towards the total, in the order they
+    /// were rolled, leaving out any dice that were dropped or rerolled.
+    pub fn get_kept_rolls(&self) -> Vec<RollResult> {
+        self.rolls.iter().filter(|r| r.kept).map(|r| r.to_result()).collect()
+    }
+
+    /// Returns the faces shown by every die with the given number of sides, in the order
+    /// they were rolled. These are the natural rolls, before any per-die modifier, cap or
+    /// floor, and before a penetrating die's follow-up is counted one less.
+    ///
+    /// # Arguments
+    /// * `sides` - The size of the dice to look for, e.g. `20` for d20s.
+    pub fn get_natural_rolls(&self, sides: i32) -> Vec<i32> {
+        self.rolls
+            .iter()
+            .filter(|r| r.sides != 0 && r.sides == sides)
+            .map(|r| r.natural)
+            .collect()
+    }
+
+    /// Returns whether any die with the given number of sides rolled at or above the
+    /// threshold, e.g. `is_critical(20, 19)` for a d20 that crits on a 19 or 20.
+    ///
+    /// # Arguments
+    /// * `sides` - The size of the dice to look for.
+    /// * `threshold` - The lowest result that counts as a critical.
+    pub fn is_critical(&self, sides: i32, threshold: i32) -> bool {
+        self.get_natural_rolls(sides).iter().any(|&result| result >= threshold)
+    }
+
+    /// Returns a map of each face rolled to the number of dice that showed it.
+    /// Constant terms are not counted.
+    pub fn get_face_distribution(&self) -> BTreeMap<i32, u32> {
+        let mut distribution = BTreeMap::new();
+        for roll in self.rolls.iter().filter(|r| r.sides != 0) {
+            *distribution.entry(roll.result).or_insert(0) += 1;
+        }
+        distribution
+    }
+
+    /// Returns a map of each size of die to the number of those dice rolled, e.g.
+    /// `{6: 2, 8: 3}` for `2d6 + 3d8`. Every die rolled is counted, including any that were
+    /// dropped, rerolled or added by explosions. Constant terms are not counted.
+    pub fn get_dice_counts(&self) -> BTreeMap<i32, usize> {
+        let mut counts = BTreeMap::new();
+        for roll in self.rolls.iter().filter(|r| r.sides != 0) {
+            *counts.entry(roll.sides).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Returns each distinct dice term of the formula as it was written, in the order they
+    /// first appear, e.g. `["1d20", "2d6"]` for `1d20 + 2d6 + 1d20 + 5`. Operators and
+    /// constant terms are left out.
+    pub fn get_dice_types(&self) -> Vec<String> {
+        let mut types: Vec<String> = Vec::new();
+        for term in &self.rolls_formula {
+            if match_token(term) <= 0 && term.parse::<i32>().is_err() && !types.contains(term) {
+                types.push(term.clone());
+            }
+        }
+        types
+    }
+
+    /// Returns the number of dice of each size that showed their highest face, e.g.
+    /// `[(6, 2), (20, 1)]` when two d6s rolled a `6` and a d20 rolled a `20`, ordered by size.
+    /// Only sizes with at least one such die are listed, and dropped or rerolled dice and
+    /// constant terms are not counted. The faces are compared before any per-die modifier.
+    pub fn get_max_rolls(&self) -> Vec<(i32, usize)> {
+        let mut counts: BTreeMap<i32, usize> = BTreeMap::new();
+        for roll in self.rolls.iter().filter(|r| r.kept && r.sides != 0 && r.natural == r.highest) {
+            *counts.entry(roll.sides).or_insert(0) += 1;
+        }
+        counts.into_iter().collect()
+    }
+
+    /// Returns the formula along with every face rolled, in the order the dice were rolled, so
+    /// that the roll can be logged and later rebuilt exactly with
+    /// [from_replay_string()](#method.from_replay_string). Any difficulty class, halving or
+    /// critical thresholds are kept too. A roll made with options other than the defaults may not rebuild the same.
+    ///
+    /// # Example output
+    ///
+    /// `2d6 + 3 = [4, 5]`, or `(4d6kh3 - 1) * 2 dc20 = [1, 6, 4, 5]`
+    pub fn to_replay_string(&self) -> String {
+        let notation = write_notation(&self.rolls_formula, self.half, self.dc, self.crit, self.fumble);
+        let faces: Vec<String> = self.faces.iter().map(|face| face.to_string()).collect();
+        format!("{} = [{}]", notation, faces.join(", "))
+    }
+
+    /// Returns a JSON object describing the roll, as a String.
+    ///
+    /// The object contains the `result` (or `null` if it can't be solved, as when the formula
+    /// divides by zero), the `sum_of_rolls`, the number of `successes` (or
+    /// `null` if there are no dice pools), every die in roll order as `{"sides", "result"}`
+    /// under `dice`, and the infix `formula` and `rolls_formula` strings.
+    ///
+    /// # Example output
+    ///
+    /// `{"result":12,"sum_of_rolls":12,"successes":null,"dice":[{"sides":6,"result":4},{"sides":6,"result":5},{"sides":0,"result":3}],"formula":"[9 + 3]","rolls_formula":"[2d6 + 3]"}`
+    pub fn to_json(&self) -> String {
+        let dice: Vec<String> = self
+            .rolls
+            .iter()
+            .map(|r| format!("{{\"sides\":{},\"result\":{}}}", r.sides, r.result))
+            .collect();
+        let result = match self.get_result_checked() {
+            Ok(result) => result.to_string(),
+            Err(_) => "null".to_string(),
+        };
+        let successes = match self.get_successes() {
+            Some(successes) => successes.to_string(),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"result\":{},\"sum_of_rolls\":{},\"successes\":{},\"dice\":[{}],\"formula\":{},\"rolls_formula\":{}}}",
+            result,
+            self.get_sum_of_rolls(),
+            successes,
+            dice.join(","),
+            json_string(&self.get_formula_string_as_infix()),
+            json_string(&self.get_rolls_formula_string_as_infix()),
+        )
     }
 
     /// Returns a formatted String showing the dice and the rolled results.
+    ///
+    /// A die whose result was modified, capped or floored also shows the face it was rolled on,
+    /// e.g. `d20 -> [15 (18)]`, and a die that was dropped or rerolled is marked with
+    /// tildes, e.g. `d6 -> [~2~]`. Negative dice are shown with their sign, e.g. `d4 -> [-3]`.
     pub fn get_rolls_string(&self) -> String {
         let mut rolls_string = String::new();
         for (i, roll) in self.rolls.iter().enumerate() {
+            let natural = if roll.percentile {
+                percentile_breakdown(roll.natural)
+            } else {
+                roll.natural.to_string()
+            };
+            let face = if roll.negative { -roll.natural } else { roll.natural };
+            let natural = if roll.negative { format!("-{}", natural) } else { natural };
+            let mut shown = if face != roll.result {
+                format!("{} ({})", roll.result, natural)
+            } else if roll.percentile {
+                format!("{} = {}", natural, roll.result)
+            } else {
+                natural
+            };
+            if !roll.kept {
+                shown = format!("~{}~", shown);
+            }
             if i == self.rolls.len() - 1 {
-                rolls_string.push_str(format!("d{} -> [{}]", roll.sides, roll.result).as_ref());
+                rolls_string.push_str(format!("d{} -> [{}]", roll.sides, shown).as_ref());
                 break;
             }
-            rolls_string.push_str(format!("d{} -> [{}], ", roll.sides, roll.result).as_ref());
+            rolls_string.push_str(format!("d{} -> [{}], ", roll.sides, shown).as_ref());
+        }
+        rolls_string
+    }
+
+    /// Returns a String showing the dice and subtotal of each term within the formula as
+    /// written, followed by the total. Dice that were dropped or rerolled are shown struck
+    /// through, as in [get_rolls_string()](#method.get_rolls_string), and constants are shown
+    /// as they are.
+    ///
+    /// # Example output
+    ///
+    /// `2d6[4+5=9] + 3 = 12`, or `(4d6kh3[~1~+6+4+5=15] - 1) * 2 = 28`
+    pub fn get_verbose_string(&self) -> String {
+        let mut verbose = self.infix_with_terms(|notation, rolls, subtotal| {
+            let dice: Vec<String> = rolls
+                .iter()
+                .map(|r| if r.kept { r.result.to_string() } else { format!("~{}~", r.result) })
+                .collect();
+            let subtotal = if dice.len() > 1 { format!("={}", subtotal) } else { String::new() };
+            format!("{}[{}{}]", notation, dice.join("+"), subtotal)
+        });
+        if let Ok(result) = self.get_result_checked() {
+            verbose.push_str(&format!(" = {}", result));
+        }
+        verbose
+    }
+
+    /// Returns a String for pasting into Markdown chat such as Discord, with the result in
+    /// bold followed by the formula as written, where each die is shown as inline code.
+    /// Dropped or rerolled dice are struck through, and any characters in the notation that
+    /// Markdown would read as formatting are escaped. Dice pools also give their successes.
+    ///
+    /// # Example output
+    ///
+    /// `**12** ⟵ 2d6 (`4`, `5`) + 3`, or `**15** ⟵ 4d6kh3 (~~`1`~~, `6`, `4`, `5`)`
+    pub fn to_markdown(&self) -> String {
+        let formula = self.infix_with_terms(|notation, rolls, _| {
+            let dice: Vec<String> = rolls
+                .iter()
+                .map(|r| if r.kept { format!("`{}`", r.result) } else { format!("~~`{}`~~", r.result) })
+                .collect();
+            format!("{} ({})", escape_markdown(notation), dice.join(", "))
+        });
+        // Any `*` left unescaped is a multiplication between terms.
+        let formula = formula.replace(" * ", " \\* ");
+        let mut markdown = match self.get_result_checked() {
+            Ok(result) => format!("**{}** ⟵ {}", result, formula),
+            Err(_) => formula,
+        };
+        if let Some(successes) = self.get_successes() {
+            let noun = if successes == 1 { "success" } else { "successes" };
+            markdown.push_str(&format!(", for **{}** {}", successes, noun));
+        }
+        markdown
+    }
+
+    /// Returns the formula as written, with the outermost brackets left out and with each
+    /// dice term shown by `dice_term` from its notation, dice and subtotal. Constant terms are
+    /// shown as they are, and rolls built from parts, which have no dice terms, show only
+    /// the values of the formula.
+    fn infix_with_terms<F: Fn(&str, &[DiceRoll], i32) -> String>(&self, dice_term: F) -> String {
+        let values = self.rolls_formula.iter().filter(|e| match_token(e) <= 0).count();
+        let mut terms: Vec<String> = Vec::new();
+        if self.groups.len() == values {
+            let mut start = 0;
+            for group in &self.groups {
+                let rolls = &self.rolls[start..start + group.len];
+                start += group.len;
+                if rolls.len() == 1 && rolls[0].sides == 0 {
+                    terms.push(rolls[0].result.to_string());
+                } else {
+                    terms.push(dice_term(&group.notation, rolls, group.subtotal));
+                }
+            }
+        } else {
+            // Without groups, as for rolls built from parts, only the values are known.
+            terms = self.formula.iter().filter(|e| match_token(e) <= 0).cloned().collect();
+        }
+
+        let mut terms = terms.into_iter();
+        let formula: Vec<String> = self
+            .rolls_formula
+            .iter()
+            .map(|e| if match_token(e) > 0 { e.clone() } else { terms.next().unwrap_or_default() })
+            .collect();
+        let infix = parse_into_infix_with(&formula, BracketStyle::Round);
+        if formula.len() > 1 {
+            // The outermost operation needs no brackets.
+            infix[1..infix.len() - 1].to_string()
+        } else {
+            infix
+        }
+    }
+
+    /// Returns a sentence describing the roll in plain English, as an alternative to the
+    /// bracketed formula strings for screen readers and chat.
+    ///
+    /// Formulas whose terms are each applied in turn, such as `2d6 + 3 - 1d4`, are
+    /// described a term at a time. Other formulas list their dice and then the formula they
+    /// were worked out with. Dice that were dropped or rerolled are shown as not counted.
+    ///
+    /// # Example output
+    ///
+    /// `Rolled 2d6 (4, 5) and added 3, for a total of 12.`
+    pub fn describe(&self) -> String {
+        // Each term of the formula has its own group, in the order they were written.
+        let mut terms: Vec<(String, bool)> = Vec::new();
+        let mut start = 0;
+        for group in &self.groups {
+            let rolls = &self.rolls[start..start + group.len];
+            start += group.len;
+            if rolls.len() == 1 && rolls[0].sides == 0 {
+                terms.push((rolls[0].result.to_string(), true));
+            } else {
+                let dice: Vec<String> = rolls
+                    .iter()
+                    .map(|r| if r.kept { r.result.to_string() } else { format!("{} not counted", r.result) })
+                    .collect();
+                terms.push((format!("{} ({})", group.notation, dice.join(", ")), false));
+            }
+        }
+
+        let values = self.rolls_formula.iter().filter(|e| match_token(e) <= 0).count();
+        let mut description = if terms.len() == values && values > 0 && is_applied_in_turn(&self.rolls_formula) {
+            let mut steps: Vec<String> = Vec::new();
+            let (ref first, constant) = terms[0];
+            steps.push(format!("{} {}", if constant { "started with" } else { "rolled" }, first));
+            for (i, (term, _)) in terms.iter().enumerate().skip(1) {
+                let verb = match Operator::from_symbol(&self.rolls_formula[2 * i]) {
+                    Some(Operator::Add) => "added",
+                    Some(Operator::Subtract) => "subtracted",
+                    Some(Operator::Multiply) => "multiplied by",
+                    _ => "divided by",
+                };
+                steps.push(format!("{} {}", verb, term));
+            }
+            join_list(&steps)
+        } else if terms.iter().any(|&(_, constant)| !constant) {
+            let dice: Vec<String> = terms.into_iter().filter(|&(_, constant)| !constant).map(|(term, _)| term).collect();
+            format!("rolled {}, then worked out {}", join_list(&dice), self.get_formula_string_as_infix())
+        } else {
+            format!("worked out {}", self.get_formula_string_as_infix())
+        };
+
+        match self.get_result_checked() {
+            Ok(result) => description.push_str(&format!(", for a total of {}", result)),
+            Err(error) => description.push_str(&format!(", which has no total as {}", error)),
+        }
+        if let Some(successes) = self.get_successes() {
+            let noun = if successes == 1 { "success" } else { "successes" };
+            description.push_str(&format!(" and {} {}", successes, noun));
+        }
+        if let (Some(dc), Some(succeeded)) = (self.dc, self.succeeded()) {
+            let verb = if succeeded { "meets" } else { "misses" };
+            description.push_str(&format!(", which {} the DC of {}", verb, dc));
+        }
+        description.push('.');
+
+        let mut chars = description.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => description,
         }
-        return rolls_string;
+    }
+
+    /// Returns the formula in postfix (rpn) format, with all dice replaced with their rolled
+    /// values, as a Vector of values and operators.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::{roll, Operator, RpnToken};
+    ///
+    /// let tokens = roll("3 * (2 + 1)".to_string()).unwrap().get_rpn_tokens();
+    /// assert_eq!(
+    ///     vec![
+    ///         RpnToken::Value(3),
+    ///         RpnToken::Value(2),
+    ///         RpnToken::Value(1),
+    ///         RpnToken::Operator(Operator::Add),
+    ///         RpnToken::Operator(Operator::Multiply),
+    ///     ],
+    ///     tokens
+    /// );
+    /// ```
+    pub fn get_rpn_tokens(&self) -> Vec<RpnToken> {
+        // Every element of the formula is either an operator or a number.
+        self.formula
+            .iter()
+            .filter_map(|element| match Operator::from_symbol(element) {
+                Some(operator) => Some(RpnToken::Operator(operator)),
+                None => element.parse().ok().map(RpnToken::Value),
+            })
+            .collect()
     }
 
     /// Returns a postfix formatted String showing the formula, with all dice replaced with their rolled values.
@@ -95,12 +1079,28 @@ impl DiceRolls {
 
             formula_string.push_str(format!("[{}] ", fragment).as_ref());
         }
-        return formula_string;
+        formula_string
     }
 
     /// Returns an infix formatted String showing the formula, with all dice replaced with their rolled values.
     pub fn get_formula_string_as_infix(&self) -> String {
-        return parse_into_infix(self.formula.clone()).replace("( ", "[").replace(" )", "]");
+        self.get_formula_string_as_infix_with(BracketStyle::Square)
+    }
+
+    /// Returns an infix formatted String showing the formula, with all dice replaced with
+    /// their rolled values, and each operation bracketed in the given style.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::{roll, BracketStyle};
+    ///
+    /// let rolls = roll("3 + 4 * 6".to_string()).unwrap();
+    /// assert_eq!("(3 + (4 * 6))", rolls.get_formula_string_as_infix_with(BracketStyle::Round));
+    /// assert_eq!("3 + 4 * 6", rolls.get_formula_string_as_infix_with(BracketStyle::None));
+    /// ```
+    pub fn get_formula_string_as_infix_with(&self, style: BracketStyle) -> String {
+        parse_into_infix_with(&self.formula, style)
     }
 
     /// Returns a postfix formatted String showing the formula with the original dice notation instead of the rolled result.
@@ -119,184 +1119,3732 @@ impl DiceRolls {
 
             formula_string.push_str(format!("[{}] ", fragment).as_ref());
         }
-        return formula_string;
+        formula_string
     }
 
     /// Returns a infix formatted String showing the formula with the original dice notation instead of the rolled result.
     pub fn get_rolls_formula_string_as_infix(&self) -> String {
-        return parse_into_infix(self.rolls_formula.clone()).replace("( ", "[").replace(" )", "]");
+        self.get_rolls_formula_string_as_infix_with(BracketStyle::Square)
+    }
+
+    /// Returns an infix formatted String showing the formula with the original dice notation
+    /// instead of the rolled result, and each operation bracketed in the given style.
+    pub fn get_rolls_formula_string_as_infix_with(&self, style: BracketStyle) -> String {
+        parse_into_infix_with(&self.rolls_formula, style)
     }
 }
 
-#[derive(Clone, Copy)]
-struct DiceRoll {
-    sides: i32,
-    result: i32,
+impl IntoIterator for DiceRolls {
+    type Item = RollResult;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter {
+            inner: self.rolls.into_iter(),
+        }
+    }
 }
 
-/// Returns a DiceRolls object based on the provided formula.
-///
-/// # Arguments
-/// * `input` - A string that provides the dice notation to work off.
-pub fn roll(input: String) -> Result<DiceRolls, Box<std::error::Error>> {
-    let formula_vector = parse_into_rpn(input.trim().as_ref());
-    return resolve_rolls_vector(formula_vector);
+impl<'a> IntoIterator for &'a DiceRolls {
+    type Item = RollResult;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
 }
 
-fn resolve_rolls_vector(rolls_vector: Vec<String>) -> Result<DiceRolls, Box<std::error::Error>> {
-    let mut formula_vector: Vec<String> = Vec::new();
-    let mut formula_vector_with_rolls: Vec<String> = Vec::new();
-    let mut dice_rolls: Vec<DiceRoll> = Vec::new();
+/// A borrowing iterator over the dice in a DiceRolls, created by
+/// [DiceRolls::iter()](struct.DiceRolls.html#method.iter).
+pub struct Iter<'a> {
+    inner: alloc::slice::Iter<'a, DiceRoll>,
+}
 
-    for element in rolls_vector {
-        // Ignore if element is recognised as a token.
-        if match_token(element.as_ref()) > 0 {
-            formula_vector.push(element.clone());
-            formula_vector_with_rolls.push(element);
-            continue;
-        }
+impl<'a> Iterator for Iter<'a> {
+    type Item = RollResult;
+
+    fn next(&mut self) -> Option<RollResult> {
+        self.inner.next().map(|roll| roll.to_result())
+    }
+}
 
-        let roll = resolve_roll_fragment(element.as_ref())?;
+/// An owning iterator over the dice in a DiceRolls.
+pub struct IntoIter {
+    inner: alloc::vec::IntoIter<DiceRoll>,
+}
 
-        for i_roll in roll.clone().rolls {
-            dice_rolls.push(i_roll);
-        }
+impl Iterator for IntoIter {
+    type Item = RollResult;
 
-        formula_vector.push(roll.get_sum_of_rolls().to_string());
-        formula_vector_with_rolls.push(element);
+    fn next(&mut self) -> Option<RollResult> {
+        self.inner.next().map(|roll| roll.to_result())
     }
+}
 
-    return Ok(DiceRolls {
-        rolls: dice_rolls,
-        formula: formula_vector,
-        rolls_formula: formula_vector_with_rolls,
-    });
+/// A single die, or constant term, from a DiceRolls.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RollResult {
+    /// The number of sides on the die, or `0` for a constant term.
+    pub sides: i32,
+    /// The value the die counted for, or the value of the constant term.
+    pub result: i32,
 }
 
-fn resolve_roll_fragment(input_fragment: &str) -> Result<DiceRolls, Box<std::error::Error>> {
-    let mut rng = thread_rng();
-    let mut dice_count_str = String::new();
-    let mut dice_sides_str = String::new();
-    let mut d_switch: bool = false;
-    let mut dice_rolls: Vec<DiceRoll> = Vec::new();
-    let mut sum: i32 = 0;
-    let dice_count: i32;
-    let dice_sides: i32;
-
-    if input_fragment.parse::<i32>().is_ok() {
-        let current_roll = DiceRoll {
-            sides: 0,
-            result: input_fragment.parse::<i32>().unwrap(),
-        };
+/// The dice rolled for a single term of a formula, such as the `2d6` of `2d6 + 3d8`.
+/// See [DiceRolls::get_groups()](struct.DiceRolls.html#method.get_groups).
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiceGroup {
+    /// The term as it was written, e.g. `2d6`.
+    pub notation: String,
+    /// The dice rolled for the term, or the value of a constant term with `sides` set to `0`.
+    pub rolls: Vec<RollResult>,
+    /// The value the term counted for.
+    pub subtotal: i32,
+    /// The tag written after the term, e.g. `fire` for `2d6[fire]`.
+    pub tag: Option<String>,
+}
 
-        dice_rolls.push(current_roll);
-        sum += current_roll.result;
-    } else {
-        for (i, c) in input_fragment.chars().enumerate() {
-            if !d_switch {
-                if c.to_string() == "d" {
-                    d_switch = true;
-                    if i == 0 {
-                        dice_count_str.push_str("1");
-                    }
-                    continue;
-                }
-                dice_count_str.push(c);
-            } else {
-                dice_sides_str.push(c);
-            }
-        }
+/// The tag a term of a formula was written with, as in `2d6[fire]`, or `Untagged` for terms
+/// without one. See [DiceRolls::get_results_by_tag()](struct.DiceRolls.html#method.get_results_by_tag).
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DiceTag {
+    Untagged,
+    Tagged(String),
+}
 
-        dice_count = dice_count_str.parse::<i32>()?;
-        let dice_sides_result = dice_sides_str.parse::<i32>();
-        if dice_sides_result.is_ok() {
-            dice_sides = dice_sides_result.unwrap();            
-        } else if match_token(dice_sides_str.as_ref()) == -3 {
-            dice_sides = 100;
-        } else {
-            return Err(Box::new(dice_sides_result.unwrap_err()));
-        }
-                
-        for _ in 0..dice_count {
-            let result = {
-                // gen_range(low, high) generates numbers in the range [low, high), 
-                // so the high number must be one higher than the highest number 
-                // that would appear on the die
-                if dice_sides == 1 {
-                    // Support "one sided" boolean dice                    
-                    rng.gen_range(0, 2)
-                } else {
-                    // Support multi-sided dice
-                    rng.gen_range(1, dice_sides + 1)
-                }
-            };
-            let current_roll = DiceRoll {
-                sides: dice_sides,
-                result,
-            };
+/// Returns a percentile roll as the tens and ones dice it was read from, as in `70 + 3`.
+fn percentile_breakdown(natural: i32) -> String {
+    let (tens, ones) = (natural % 100 / 10, natural % 10);
+    format!("{}0 + {}", tens, ones)
+}
 
-            dice_rolls.push(current_roll);
-            sum += current_roll.result;
+/// Escapes the characters that Markdown, as used in chat such as Discord, reads as formatting.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if "\\`*_~|>[]".contains(c) {
+            escaped.push('\\');
         }
+        escaped.push(c);
     }
+    escaped
+}
 
-    return Ok(DiceRolls {
-        rolls: dice_rolls,
-        formula: vec![sum.to_string()],
-        rolls_formula: vec![input_fragment.to_string()],
-    });
+/// Returns whether each operator of a postfix formula applies a single new term to
+/// everything before it, as in `["2d6", "3", "+", "1d4", "-"]`.
+fn is_applied_in_turn(formula: &[String]) -> bool {
+    formula.iter().enumerate().all(|(i, e)| (match_token(e) > 0) == (i > 0 && i % 2 == 0))
+        && formula.len() % 2 == 1
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Returns the items joined into an English list, as in `a, b and c`.
+fn join_list(items: &[String]) -> String {
+    match items.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        Some((last, _)) => last.to_string(),
+        None => String::new(),
+    }
+}
 
-    #[test]
-    fn roll_from_string() {
-        println!();
-        let roll0 = roll("2d4".to_string()).unwrap();
-        println!("Rolls:             {}", roll0.get_rolls_string());
-        println!("RPN Formula:       {}", roll0.get_formula_string_as_rpn());
-        println!("Formula:           {}", roll0.get_formula_string_as_infix());
-        println!("RPN Rolls Formula: {}", roll0.get_rolls_formula_string_as_rpn());
-        println!("Rolls Formula:     {}", roll0.get_rolls_formula_string_as_infix());
-        println!("Result:            {}", roll0.get_result());
-        println!();
+/// Two rolls are equal when they have the same result, however different their dice, and
+/// are ordered by their results. See [DiceRolls::compare()](struct.DiceRolls.html#method.compare).
+impl PartialEq for DiceRolls {
+    fn eq(&self, other: &DiceRolls) -> bool {
+        self.compare(other) == Ordering::Equal
+    }
+}
 
-        let roll1 = roll("(2d6 - 1d8) * (3d4 + 4d12)".to_string()).unwrap();
-        println!("Rolls:             {}", roll1.get_rolls_string());
-        println!("RPN Formula:       {}", roll1.get_formula_string_as_rpn());
-        println!("Formula:           {}", roll1.get_formula_string_as_infix());
-        println!("RPN Rolls Formula: {}", roll1.get_rolls_formula_string_as_rpn());
-        println!("Rolls Formula:     {}", roll1.get_rolls_formula_string_as_infix());
-        println!("Result:            {}", roll1.get_result());
-        println!();
+impl PartialOrd for DiceRolls {
+    fn partial_cmp(&self, other: &DiceRolls) -> Option<Ordering> {
+        Some(self.compare(other))
+    }
+}
 
-        let roll2 = roll("3d% + d%".to_string()).unwrap();
-        println!("Rolls:             {}", roll2.get_rolls_string());
-        println!("RPN Formula:       {}", roll2.get_formula_string_as_rpn());
-        println!("Formula:           {}", roll2.get_formula_string_as_infix());
-        println!("RPN Rolls Formula: {}", roll2.get_rolls_formula_string_as_rpn());
-        println!("Rolls Formula:     {}", roll2.get_rolls_formula_string_as_infix());
-        println!("Result:            {}", roll2.get_result());
-        println!();
+/// Where the dice of a single term lie within the rolls of a DiceRolls.
+#[derive(Clone)]
+struct GroupSpan {
+    notation: String,
+    len: usize,
+    subtotal: i32,
+    tag: Option<String>,
+}
 
-        let roll3 = roll("d100 / 15".to_string()).unwrap();
-        println!("Rolls:             {}", roll3.get_rolls_string());
-        println!("RPN Formula:       {}", roll3.get_formula_string_as_rpn());
+#[derive(Clone, Copy)]
+struct DiceRoll {
+    sides: i32,
+    /// The highest face the die can show, such as `90` for a `d00`, or `0` for a constant.
+    highest: i32,
+    result: i32,
+    /// The face the die showed, before any per-die modifier, cap or floor was applied to its result.
+    natural: i32,
+    /// Whether the die counts towards the total, rather than having been dropped or rerolled.
+    kept: bool,
+    /// Whether the die is a percentile die rolled as a tens die and a ones die.
+    percentile: bool,
+    /// Whether the die counts against the total, as the dice of `-2d4` do.
+    negative: bool,
+}
+
+impl DiceRoll {
+    fn to_result(self) -> RollResult {
+        RollResult {
+            sides: self.sides,
+            result: self.result,
+        }
+    }
+}
+
+/// Returns a DiceRolls object based on the provided formula.
+///
+/// Empty or whitespace-only input returns `RollError::EmptyExpression`, an
+/// operator without both of its operands returns `RollError::MissingOperand`, and
+/// a bracket without a partner returns `RollError::UnbalancedParentheses`.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+#[cfg(feature = "std")]
+pub fn roll(input: String) -> Result<DiceRolls, RollError> {
+    roll_with_options(input, &RollOptions::default())
+}
+
+/// Returns a DiceRolls object based on the provided formula, with each die showing the
+/// next of the `forced` faces instead of a random one. This makes it easy to reproduce
+/// particular rolls, such as every die showing its maximum, in tests and screenshots.
+///
+/// Faces are used as given, in the order the dice are rolled, including any dice rolled
+/// for rerolls, explosions, or bracketed counts and sides. Once the faces run out, any
+/// remaining dice are rolled randomly.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+/// * `forced` - The faces to give the dice, in order.
+///
+/// # Example
+///
+/// ```
+/// use rfyl::roll_with_forced;
+///
+/// assert_eq!(25, roll_with_forced("1d20 + 5".to_string(), &[20]).unwrap().get_result());
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_with_forced(input: String, forced: &[i32]) -> Result<DiceRolls, RollError> {
+    let mut rolls = ForcedRolls {
+        forced: forced.iter(),
+        fallback: thread_rng(),
+    };
+    roll_with_roller(&input, &RollOptions::default(), &mut rolls)
+}
+
+/// Returns a DiceRolls object based on the provided formula, with each die showing its
+/// average rather than a random face, as when taking the average for monster hit points or
+/// damage in a stat block. The formula is then solved as usual, and the roll can be shown
+/// and replayed like any other.
+///
+/// A die whose average falls between two faces, as a d6's `3.5` does, is rounded down and
+/// up in turn, starting down, so that `2d6` (or `1d6 + 1d6`) averages `7` and `3d6` averages
+/// `10`, as a stat block rounding down the average of the whole roll would give.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+///
+/// # Example
+///
+/// ```
+/// use rfyl::roll_average;
+///
+/// assert_eq!(12, roll_average("2d8 + 3".to_string()).unwrap().get_result());
+/// assert_eq!(17, roll_average("5d6".to_string()).unwrap().get_result());
+/// ```
+pub fn roll_average(input: String) -> Result<DiceRolls, RollError> {
+    roll_with_roller(&input, &RollOptions::default(), &mut AverageFaces { round_up: false })
+}
+
+/// Returns a DiceRolls object based on the provided formula, with its first `d20` rolled
+/// twice and the higher die kept. Both dice can be seen in the rolls, with the lower one
+/// marked as dropped. A formula without a plain `d20` (or `1d20`) returns `RollError::MissingD20`.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off, e.g. `1d20 + 5`.
+///
+/// # Example
+///
+/// ```
+/// let roll = rfyl::roll_with_advantage("1d20 + 5".to_string()).unwrap();
+/// assert_eq!(2, roll.iter().filter(|r| r.sides == 20).count());
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_with_advantage(input: String) -> Result<DiceRolls, RollError> {
+    evaluate(&parse_expression(&input)?.with_advantage()?)
+}
+
+/// Returns a DiceRolls object based on the provided formula, with its first `d20` rolled
+/// twice and the lower die kept. A formula without a plain `d20` (or `1d20`) returns
+/// `RollError::MissingD20`. See [roll_with_advantage()](fn.roll_with_advantage.html).
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off, e.g. `1d20 + 5`.
+#[cfg(feature = "std")]
+pub fn roll_with_disadvantage(input: String) -> Result<DiceRolls, RollError> {
+    evaluate(&parse_expression(&input)?.with_disadvantage()?)
+}
+
+/// Returns a DiceRolls object for a Savage Worlds trait roll: an exploding trait die and an
+/// exploding wild `d6` are rolled, and the higher of their totals is kept, with the trait
+/// die winning ties.
+///
+/// The groups of the roll give the total of each die, trait die first, and the dice of the
+/// lower total are shown as dropped. The formula is that of the die kept, so
+/// [reroll()](struct.DiceRolls.html#method.reroll) rolls only that die again.
+///
+/// Trait dice of two or three sides are rolled as given. Fewer sides return
+/// `RollError::InvalidDiceSides`, as a one-sided die would explode on every roll.
+///
+/// # Arguments
+/// * `trait_sides` - The sides of the trait die, usually from `4` up to `12`.
+///
+/// # Example
+///
+/// ```
+/// let roll = rfyl::roll_savage(8).unwrap();
+/// let groups = roll.get_groups();
+/// assert_eq!("1d8!", groups[0].notation);
+/// assert_eq!("1d6!", groups[1].notation);
+/// assert_eq!(roll.get_result(), groups[0].subtotal.max(groups[1].subtotal));
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_savage(trait_sides: i32) -> Result<DiceRolls, RollError> {
+    let notation = format!("1d{}!", trait_sides);
+    if trait_sides < 2 {
+        return Err(RollError::InvalidDiceSides {
+            fragment: notation,
+            position: 0,
+        });
+    }
+
+    let options = RollOptions::default();
+    let mut rng = thread_rng();
+    let trait_die = roll_with_roller(&notation, &options, &mut rng)?;
+    let wild_die = roll_with_roller("1d6!", &options, &mut rng)?;
+    let trait_wins = trait_die.get_result() >= wild_die.get_result();
+
+    // The dice of the lower total are shown as dropped.
+    let (kept, dropped, dropped_start) = if trait_wins {
+        (&trait_die, &wild_die, trait_die.rolls.len())
+    } else {
+        (&wild_die, &trait_die, 0)
+    };
+    let mut rolls = trait_die.rolls.clone();
+    rolls.extend(wild_die.rolls.iter().cloned());
+    for roll in &mut rolls[dropped_start..dropped_start + dropped.rolls.len()] {
+        roll.kept = false;
+    }
+
+    let mut groups = trait_die.groups.clone();
+    groups.extend(wild_die.groups.iter().cloned());
+    Ok(DiceRolls {
+        rolls,
+        formula: kept.formula.clone(),
+        rolls_formula: kept.rolls_formula.clone(),
+        pool: None,
+        dc: None,
+        half: None,
+        crit: None,
+        fumble: None,
+        faces: kept.faces.clone(),
+        groups,
+        options,
+    })
+}
+
+/// Rolls the provided formula a number of times, and returns its results combined as requested.
+/// The formula is only read once. Rolling zero times returns `RollError::EmptyExpression`.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+/// * `times` - How many times to roll the formula.
+/// * `mode` - How the results are combined. See [AggregateMode](enum.AggregateMode.html).
+///
+/// # Example
+///
+/// ```
+/// use rfyl::{roll_aggregate, AggregateMode};
+///
+/// // Roll 4d6, keeping the highest three, six times and total the results.
+/// let total = roll_aggregate("4d6kh3".to_string(), 6, AggregateMode::Sum).unwrap();
+/// assert!(total >= 18 && total <= 108);
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_aggregate(input: String, times: usize, mode: AggregateMode) -> Result<i32, RollError> {
+    let expression = parse_expression(&input)?;
+    let options = RollOptions::default();
+    let mut rng = thread_rng();
+    let mut results: Vec<i32> = Vec::with_capacity(times);
+    for _ in 0..times {
+        results.push(evaluate_with_roller(&expression, &options, &mut rng)?.get_result_checked()?);
+    }
+    if results.is_empty() {
+        return Err(RollError::EmptyExpression);
+    }
+
+    let sum = || results.iter().try_fold(0i32, |sum, &result| sum.checked_add(result)).ok_or(RollError::Overflow);
+    match mode {
+        AggregateMode::Sum => sum(),
+        AggregateMode::Average => Ok(RoundingMode::Nearest.divide(sum()?, results.len() as i32)),
+        AggregateMode::Max => Ok(results.iter().cloned().max().unwrap_or(0)),
+        AggregateMode::Min => Ok(results.iter().cloned().min().unwrap_or(0)),
+    }
+}
+
+/// Rolls the provided formula a number of times, and returns the roll with the highest
+/// result, dice and all, as for abilities that roll a whole formula several times and keep
+/// the best. The formula is only read once, and the earliest roll wins any tie. Rolling zero
+/// times returns `RollError::EmptyExpression`.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+/// * `times` - How many times to roll the formula.
+///
+/// # Example
+///
+/// ```
+/// // Roll 4d6, keeping the highest three, twice and keep the better of the two.
+/// let best = rfyl::roll_best_of("4d6kh3".to_string(), 2).unwrap();
+/// assert_eq!(best.get_sum_of_rolls(), best.get_result());
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_best_of(input: String, times: usize) -> Result<DiceRolls, RollError> {
+    roll_extreme_of(&input, times, Ordering::Greater)
+}
+
+/// Rolls the provided formula a number of times, and returns the roll with the lowest
+/// result, as [roll_best_of()](fn.roll_best_of.html) does for the highest.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+/// * `times` - How many times to roll the formula.
+#[cfg(feature = "std")]
+pub fn roll_worst_of(input: String, times: usize) -> Result<DiceRolls, RollError> {
+    roll_extreme_of(&input, times, Ordering::Less)
+}
+
+/// Rolls `input` `times` times, keeping the first roll whose result compares as `wanted`
+/// with every roll kept before it.
+#[cfg(feature = "std")]
+fn roll_extreme_of(input: &str, times: usize, wanted: Ordering) -> Result<DiceRolls, RollError> {
+    let expression = parse_expression(input)?;
+    let options = RollOptions::default();
+    let mut rng = thread_rng();
+    let mut extreme: Option<(i32, DiceRolls)> = None;
+    for _ in 0..times {
+        let rolls = evaluate_with_roller(&expression, &options, &mut rng)?;
+        let result = rolls.get_result_checked()?;
+        if extreme.as_ref().map_or(true, |&(kept, _)| result.cmp(&kept) == wanted) {
+            extreme = Some((result, rolls));
+        }
+    }
+    extreme.map(|(_, rolls)| rolls).ok_or(RollError::EmptyExpression)
+}
+
+/// Rolls each of several formulas written together, such as an attack and its damage, and
+/// returns their rolls in order.
+///
+/// The formulas are split at each `separator` outside of any brackets or braces, so a
+/// separator inside brackets keeps its usual meaning. The usual separator is
+/// [DEFAULT_MULTI_SEPARATOR](constant.DEFAULT_MULTI_SEPARATOR.html) (`;`), but an operator
+/// such as `/` may be used instead, in which case every `/` outside of brackets separates
+/// formulas rather than dividing, and dividing needs brackets, as in `(1d6 / 2)`.
+///
+/// If any formula can't be rolled, its error is returned, with any position given as a
+/// character offset in the whole of `input`.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+/// * `separator` - The character written between formulas.
+///
+/// # Example
+///
+/// ```
+/// use rfyl::roll_multi;
+///
+/// let rolls = roll_multi("1d20 + 5 / (1d8 / 2) + 2".to_string(), '/').unwrap();
+/// assert_eq!(2, rolls.len());
+/// assert!(rolls[0].get_result() >= 6 && rolls[0].get_result() <= 25);
+/// assert!(rolls[1].get_result() >= 3 && rolls[1].get_result() <= 6);
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_multi(input: String, separator: char) -> Result<Vec<DiceRolls>, RollError> {
+    let options = RollOptions::default();
+    let mut rng = thread_rng();
+    split_top_level(&input, separator)
+        .into_iter()
+        .map(|(offset, formula)| roll_with_roller(formula, &options, &mut rng).map_err(|e| e.offset(offset)))
+        .collect()
+}
+
+/// Rolls each line of `input` as a separate formula, and returns the result of each in order.
+/// An error on one line is returned in its place without stopping the other lines.
+///
+/// Blank lines, and comment lines whose first character other than whitespace is `#`, are
+/// skipped without a result, so the results may be fewer than the lines. Any error
+/// positions are character offsets within the line.
+///
+/// # Arguments
+/// * `input` - A string with a formula on each line.
+///
+/// # Example
+///
+/// ```
+/// use rfyl::roll_lines;
+///
+/// let results = roll_lines("# Attack\n1d20 + 5\n\n2d6 + @");
+/// assert_eq!(2, results.len());
+/// assert!(results[0].is_ok());
+/// assert!(results[1].is_err());
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_lines(input: &str) -> Vec<Result<DiceRolls, RollError>> {
+    let options = RollOptions::default();
+    let mut rng = thread_rng();
+    input
+        .lines()
+        .filter(|line| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|line| roll_with_roller(line, &options, &mut rng))
+        .collect()
+}
+
+/// A running total of formulas rolled one after another, such as the damage taken over a
+/// fight, along with every roll that went into it.
+///
+/// # Example
+///
+/// ```
+/// use rfyl::RollAccumulator;
+///
+/// let mut damage = RollAccumulator::new();
+/// damage.add("2d6 + 3").unwrap();
+/// damage.add("1d8").unwrap();
+/// assert_eq!(2, damage.history().len());
+/// assert_eq!(damage.history().iter().map(|roll| roll.get_result()).sum::<i32>(), damage.total());
+/// ```
+#[derive(Clone, Default)]
+pub struct RollAccumulator {
+    history: Vec<DiceRolls>,
+    total: i32,
+    #[cfg(feature = "std")]
+    options: RollOptions,
+}
+
+impl RollAccumulator {
+    /// Returns an accumulator with no rolls and a total of `0`, rolling with the default options.
+    pub fn new() -> RollAccumulator {
+        RollAccumulator::default()
+    }
+
+    /// Returns an accumulator with no rolls and a total of `0`, rolling each formula added
+    /// with the provided options. See [RollOptions](struct.RollOptions.html).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rfyl::{RollAccumulator, RollOptions, RoundingMode};
+    ///
+    /// let mut halved = RollAccumulator::with_options(RollOptions { rounding: RoundingMode::Floor, ..RollOptions::default() });
+    /// halved.add("7 / 2").unwrap();
+    /// assert_eq!(3, halved.total());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn with_options(options: RollOptions) -> RollAccumulator {
+        RollAccumulator {
+            options,
+            ..RollAccumulator::default()
+        }
+    }
+
+    /// Rolls `input` and adds its result to the total, returning the roll. A formula that
+    /// can't be rolled, or whose result can't be solved or would take the total out of
+    /// range, returns an error and leaves the total and history as they were.
+    ///
+    /// # Arguments
+    /// * `input` - A string that provides the dice notation to work off.
+    #[cfg(feature = "std")]
+    pub fn add(&mut self, input: &str) -> Result<&DiceRolls, RollError> {
+        let rolls = roll_with_options(input.to_string(), &self.options)?;
+        self.total = self.total.checked_add(rolls.get_result_checked()?).ok_or(RollError::Overflow)?;
+        self.history.push(rolls);
+        Ok(&self.history[self.history.len() - 1])
+    }
+
+    /// Returns the sum of the results of every roll added so far.
+    pub fn total(&self) -> i32 {
+        self.total
+    }
+
+    /// Returns every roll added so far, in the order they were added.
+    pub fn history(&self) -> &[DiceRolls] {
+        &self.history
+    }
+}
+
+/// The successes and failures counted by the dice pools of a roll, such as `6d10>=7f1`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PoolResult {
+    /// The number of dice meeting the success target, with `dbl` dice counting twice.
+    pub successes: i32,
+    /// The number of dice meeting the failure target.
+    pub failures: i32,
+    /// The successes less the failures.
+    pub net: i32,
+    /// Whether any failures were rolled without the successes outnumbering them.
+    pub botch: bool,
+}
+
+impl PoolResult {
+    fn new(successes: i32, failures: i32) -> PoolResult {
+        PoolResult {
+            successes,
+            failures,
+            net: successes - failures,
+            botch: failures > 0 && successes <= failures,
+        }
+    }
+
+    fn combine(self, other: PoolResult) -> PoolResult {
+        PoolResult::new(self.successes + other.successes, self.failures + other.failures)
+    }
+}
+
+/// How well a roll succeeded, from a critical failure up to a critical success, as given by
+/// [DiceRolls::get_success_tier()](struct.DiceRolls.html#method.get_success_tier).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SuccessTier {
+    /// The result was at or below the `fumble<n>` threshold.
+    CritFail,
+    /// The result was below the difficulty class.
+    Fail,
+    /// The result met the difficulty class.
+    Success,
+    /// The result was at or above the `crit<n>` threshold.
+    CritSuccess,
+}
+
+/// The outcome of a d20 roll with automatic misses and hits, as given by
+/// [DiceRolls::get_d20_outcome()](struct.DiceRolls.html#method.get_d20_outcome).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum D20Outcome {
+    /// The d20 showed a natural `1`.
+    AutoFail,
+    /// The d20 showed a natural `20`.
+    AutoSuccess,
+    /// The d20 showed any other face, for the given total.
+    Normal(i32),
+}
+
+/// The outcome of an opposed roll, made with [roll_opposed()](fn.roll_opposed.html).
+#[derive(Clone)]
+pub struct OpposedResult {
+    /// The attacker's roll.
+    pub attacker: DiceRolls,
+    /// The defender's roll.
+    pub defender: DiceRolls,
+    /// Which side won, and by how much.
+    pub outcome: OpposedOutcome,
+}
+
+/// Which side of an opposed roll won, along with the margin they won by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OpposedOutcome {
+    Attacker(i32),
+    Defender(i32),
+    /// Both sides rolled the same result. Most systems break ties some other way, such as
+    /// in favour of the defender, so neither side is chosen here.
+    Tie,
+}
+
+/// Rolls two formulas against each other, returning both rolls and which one was higher.
+///
+/// # Arguments
+/// * `attacker` - A string that provides the dice notation for one side, e.g. `1d20 + 5`.
+/// * `defender` - A string that provides the dice notation for the other side, e.g. `1d20 + 3`.
+///
+/// # Example
+///
+/// ```
+/// use rfyl::{roll_opposed, OpposedOutcome};
+///
+/// let opposed = roll_opposed("1d20 + 5".to_string(), "1d20 + 3".to_string()).unwrap();
+/// match opposed.outcome {
+///     OpposedOutcome::Attacker(margin) => println!("The attacker won by {}", margin),
+///     OpposedOutcome::Defender(margin) => println!("The defender won by {}", margin),
+///     OpposedOutcome::Tie => println!("It's a tie"),
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_opposed(attacker: String, defender: String) -> Result<OpposedResult, RollError> {
+    let attacker = roll(attacker)?;
+    let defender = roll(defender)?;
+    let margin = attacker
+        .get_result_checked()?
+        .checked_sub(defender.get_result_checked()?)
+        .ok_or(RollError::Overflow)?;
+    let outcome = match margin {
+        m if m > 0 => OpposedOutcome::Attacker(m),
+        m if m < 0 => OpposedOutcome::Defender(m.checked_neg().ok_or(RollError::Overflow)?),
+        _ => OpposedOutcome::Tie,
+    };
+    Ok(OpposedResult {
+        attacker,
+        defender,
+        outcome,
+    })
+}
+
+/// Returns a DiceRolls object rolled from tokens that have already been split up, skipping
+/// the tokenizer, as for a REPL or editor that does its own lexing. See
+/// [parse_tokens()](fn.parse_tokens.html).
+///
+/// # Arguments
+/// * `tokens` - The tokens of the formula in the order they were written, e.g. `["2d6", "+", "3"]`.
+///
+/// # Example
+///
+/// ```
+/// let tokens = vec!["2d6".to_string(), "+".to_string(), "3".to_string()];
+/// let result = rfyl::roll_tokens(tokens).unwrap().get_result();
+/// assert!(result >= 5 && result <= 15);
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_tokens<I: IntoIterator<Item = String>>(tokens: I) -> Result<DiceRolls, RollError> {
+    evaluate(&parse_tokens(tokens)?)
+}
+
+/// Returns only the result of rolling the provided formula, as
+/// [DiceRolls::get_result()](struct.DiceRolls.html#method.get_result) would. A formula
+/// that can't be solved, such as one dividing by zero, returns an error rather than panicking.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+///
+/// # Example
+///
+/// ```
+/// let result = rfyl::roll_result("2d6 + 3".to_string()).unwrap();
+/// assert!(result >= 5 && result <= 15);
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_result(input: String) -> Result<i32, RollError> {
+    roll(input)?.get_result_checked()
+}
+
+/// Returns only the sum of the dice and constants rolled for the provided formula,
+/// ignoring its operators, as
+/// [DiceRolls::get_sum_of_rolls()](struct.DiceRolls.html#method.get_sum_of_rolls) would.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+#[cfg(feature = "std")]
+pub fn roll_sum(input: String) -> Result<i32, RollError> {
+    Ok(roll(input)?.get_sum_of_rolls())
+}
+
+/// Returns a DiceRolls object based on the provided formula, rolled and solved
+/// according to the provided options.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+/// * `options` - The settings to roll with. See [RollOptions](struct.RollOptions.html).
+///
+/// # Example
+///
+/// ```
+/// use rfyl::{roll_with_options, RollOptions, RoundingMode};
+///
+/// let options = RollOptions { rounding: RoundingMode::Floor, ..RollOptions::default() };
+/// assert_eq!(3, roll_with_options("7 / 2".to_string(), &options).unwrap().get_result());
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_with_options(input: String, options: &RollOptions) -> Result<DiceRolls, RollError> {
+    match options.seed {
+        Some(seed) => roll_with_roller(&input, options, &mut StdRng::seed_from_u64(seed)),
+        None => roll_with_roller(&input, options, &mut thread_rng()),
+    }
+}
+
+/// The intermediate steps taken to roll a formula, for debugging unexpected results.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RollTrace {
+    /// The values, operators and brackets the input was split into.
+    pub tokens: Vec<String>,
+    /// The tokens rearranged into postfix (rpn) order by
+    /// [rpn::parse_into_rpn()](rpn/fn.parse_into_rpn.html).
+    pub rpn: Vec<String>,
+    /// The postfix formula with each value replaced by its rolled result, as it is solved.
+    pub formula: Vec<String>,
+}
+
+/// Returns a DiceRolls object based on the provided formula, along with a RollTrace
+/// showing how the formula was read and rolled.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+///
+/// # Example
+///
+/// ```
+/// use rfyl::roll_with_trace;
+///
+/// let (roll, trace) = roll_with_trace("2 * (1d4 + 1)".to_string()).unwrap();
+/// assert_eq!(vec!["2", "*", "(", "1d4", "+", "1", ")"], trace.tokens);
+/// assert_eq!(vec!["2", "1d4", "1", "+", "*"], trace.rpn);
+/// assert_eq!(roll.get_result(), rfyl::rpn::solve_rpn_formula(trace.formula));
+///
+/// // Modifiers at the end of the formula aren't tokens of it.
+/// let (_, trace) = roll_with_trace("1d20 + 5 dc15".to_string()).unwrap();
+/// assert_eq!(vec!["1d20", "+", "5"], trace.tokens);
+/// assert_eq!(vec!["1d20", "5", "+"], trace.rpn);
+/// ```
+#[cfg(feature = "std")]
+pub fn roll_with_trace(input: String) -> Result<(DiceRolls, RollTrace), RollError> {
+    let rolls = roll(input.clone())?;
+    let trace = RollTrace {
+        tokens: tokenize(split_modifiers(input.trim()).0).into_iter().map(|token| token.text).collect(),
+        rpn: rolls.rolls_formula.clone(),
+        formula: rolls.formula.clone(),
+    };
+    Ok((rolls, trace))
+}
+
+/// Returns a DiceRolls object from rolling an expression that has already been read or
+/// put together with an [ExpressionBuilder](struct.ExpressionBuilder.html).
+///
+/// # Arguments
+/// * `expression` - The expression to roll.
+///
+/// # Example
+///
+/// ```
+/// use rfyl::{evaluate, parse_expression};
+///
+/// let expression = parse_expression("1d20 + 5 dc15").unwrap();
+/// let roll = evaluate(&expression).unwrap();
+/// assert_eq!(Some(roll.get_result() >= 15), roll.succeeded());
+/// ```
+#[cfg(feature = "std")]
+pub fn evaluate(expression: &ParsedExpression) -> Result<DiceRolls, RollError> {
+    evaluate_with_options(expression, &RollOptions::default())
+}
+
+/// Returns a DiceRolls object from rolling an expression according to the provided options.
+///
+/// # Arguments
+/// * `expression` - The expression to roll.
+/// * `options` - The settings to roll with. See [RollOptions](struct.RollOptions.html).
+#[cfg(feature = "std")]
+pub fn evaluate_with_options(expression: &ParsedExpression, options: &RollOptions) -> Result<DiceRolls, RollError> {
+    match options.seed {
+        Some(seed) => evaluate_with_roller(expression, options, &mut StdRng::seed_from_u64(seed)),
+        None => evaluate_with_roller(expression, options, &mut thread_rng()),
+    }
+}
+
+/// Returns a DiceRolls object based on the provided formula, with the dice rolled by the
+/// provided random number generator. This is available without the `std` feature, for
+/// rolling where there is no `thread_rng()`. The `seed` of the options is ignored.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+/// * `options` - The settings to roll with. See [RollOptions](struct.RollOptions.html).
+/// * `rng` - The random number generator to roll the dice with.
+///
+/// # Example
+///
+/// ```
+/// extern crate rand;
+/// extern crate rfyl;
+///
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+/// use rfyl::{roll_with_rng, RollOptions};
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let roll = roll_with_rng("2d6 + 3", &RollOptions::default(), &mut rng).unwrap();
+/// assert!(roll.get_result() >= 5 && roll.get_result() <= 15);
+/// ```
+pub fn roll_with_rng<R: Rng>(input: &str, options: &RollOptions, rng: &mut R) -> Result<DiceRolls, RollError> {
+    roll_with_roller(input, options, rng)
+}
+
+/// Returns a DiceRolls object from rolling an expression with the provided random number
+/// generator. This is available without the `std` feature. The `seed` of the options is ignored.
+///
+/// # Arguments
+/// * `expression` - The expression to roll.
+/// * `options` - The settings to roll with. See [RollOptions](struct.RollOptions.html).
+/// * `rng` - The random number generator to roll the dice with.
+pub fn evaluate_with_rng<R: Rng>(
+    expression: &ParsedExpression,
+    options: &RollOptions,
+    rng: &mut R,
+) -> Result<DiceRolls, RollError> {
+    evaluate_with_roller(expression, options, rng)
+}
+
+/// Checks that the provided formula could be rolled, without rolling any dice. This is
+/// cheap enough to call as the formula is typed, e.g. to enable a button to roll it.
+///
+/// The formula returns the same error as rolling it would for an unbalanced bracket, an
+/// operator without its operands, or a term that isn't a constant or dice notation,
+/// including any bracketed within a term. Errors that depend on the dice rolled, such as
+/// dividing by a total of zero, or rolled sides of less than one, are not found.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+///
+/// # Example
+///
+/// ```
+/// use rfyl::{validate, RollError};
+///
+/// assert_eq!(Ok(()), validate("(4d6kh3 + 2) * 2 dc12"));
+/// assert_eq!(Err(RollError::MissingOperand("+".to_string())), validate("2d6 +"));
+/// ```
+pub fn validate(input: &str) -> Result<(), RollError> {
+    let expression = parse_expression(input)?;
+    for (term, &position) in expression.get_formula().iter().zip(expression.get_positions()) {
+        if match_token(term) <= 0 {
+            let fragment = parse_fragment(term).map_err(|e| e.at(position))?;
+            validate_fragment(&fragment, term).map_err(|e| e.at(position))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether the provided formula could be rolled, without rolling any dice.
+/// See [validate()](fn.validate.html).
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+pub fn is_valid(input: &str) -> bool {
+    validate(input).is_ok()
+}
+
+/// Checks the terms and formulas bracketed within a fragment, and any fixed sides.
+fn validate_fragment(fragment: &Fragment, input_fragment: &str) -> Result<(), RollError> {
+    match *fragment {
+        Fragment::Constant(_) => Ok(()),
+        Fragment::Dice(ref dice) => {
+            if let Amount::Fixed(sides) = dice.sides {
+                if sides < 1 {
+                    return Err(RollError::InvalidDiceSides {
+                        fragment: input_fragment.to_string(),
+                        position: 0,
+                    });
+                }
+            }
+            if dice.sides == Amount::Default {
+                return Err(RollError::InvalidFragment {
+                    fragment: input_fragment.to_string(),
+                    position: 0,
+                });
+            }
+            for amount in &[&dice.count, &dice.sides] {
+                if let Amount::Expression(ref expression) = **amount {
+                    validate(expression)?;
+                }
+            }
+            Ok(())
+        }
+        Fragment::Set(ref set) => set.terms.iter().try_for_each(|term| validate_fragment(term, input_fragment)),
+        Fragment::Repeat(ref repeat) => validate(&repeat.expression),
+        Fragment::Conditional(ref conditional) => {
+            validate(&conditional.condition)?;
+            validate(&conditional.then)?;
+            validate(&conditional.otherwise)
+        }
+    }
+}
+
+/// Returns how many dice the provided formula rolls, without rolling any, as a cheap check
+/// before rolling a formula that may be too large to show, e.g. `400` for `100d6 + 300d4`.
+///
+/// Dice are counted as written, before any rerolls or explosions, and percentile, boolean,
+/// average and digit dice each count as a single die. The dice of a repeated formula count
+/// once for each time it is rolled, and a conditional counts its condition along with
+/// whichever of its branches rolls more dice. A count given as a bracketed formula, as in
+/// `(1d4)d6`, counts for its own dice and the most dice it could roll, where the bracketed
+/// formula only adds and subtracts constants and dice without modifiers. Other bracketed
+/// counts count for their own dice alone.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(Ok(5), rfyl::count_dice("4d6kh3 + d% + 2"));
+/// assert_eq!(Ok(5), rfyl::count_dice("(1d4)d6"));
+/// ```
+pub fn count_dice(input: &str) -> Result<u64, RollError> {
+    let expression = parse_expression(input)?;
+    let mut total: u64 = 0;
+    for (term, &position) in expression.get_formula().iter().zip(expression.get_positions()) {
+        if match_token(term) <= 0 {
+            let fragment = parse_fragment(term).map_err(|e| e.at(position))?;
+            total = total.saturating_add(count_fragment_dice(&fragment).map_err(|e| e.at(position))?);
+        }
+    }
+    Ok(total)
+}
+
+/// Counts the dice of a single fragment. See [count_dice()](fn.count_dice.html).
+fn count_fragment_dice(fragment: &Fragment) -> Result<u64, RollError> {
+    match *fragment {
+        Fragment::Constant(_) => Ok(0),
+        Fragment::Dice(ref dice) => {
+            let mut total = match dice.count {
+                Amount::Expression(ref expression) => {
+                    let most = parse_expression(expression)?;
+                    let most = highest_total(most.get_formula()).unwrap_or(0).max(0) as u64;
+                    count_dice(expression)?.saturating_add(most)
+                }
+                Amount::Fixed(count) => count.max(0) as u64,
+                Amount::Default => 1,
+            };
+            if let Amount::Expression(ref expression) = dice.sides {
+                total = total.saturating_add(count_dice(expression)?);
+            }
+            Ok(total)
+        }
+        Fragment::Set(ref set) => set
+            .terms
+            .iter()
+            .try_fold(0u64, |total, term| Ok(total.saturating_add(count_fragment_dice(term)?))),
+        Fragment::Repeat(ref repeat) => {
+            Ok(u64::from(repeat.times.unsigned_abs()).saturating_mul(count_dice(&repeat.expression)?))
+        }
+        Fragment::Conditional(ref conditional) => {
+            let branches = count_dice(&conditional.then)?.max(count_dice(&conditional.otherwise)?);
+            Ok(count_dice(&conditional.condition)?.saturating_add(branches))
+        }
+    }
+}
+
+/// Returns the highest total a formula in RPN could have, if it only adds and subtracts
+/// constants and dice without modifiers. Dice are taken to show no less than `0`.
+fn highest_total(rolls_formula: &[String]) -> Option<i64> {
+    let range = fold_additive(
+        rolls_formula,
+        |term| match parse_fragment(term).ok()? {
+            Fragment::Constant(constant) => Some((i64::from(constant), i64::from(constant))),
+            Fragment::Dice(ref dice) if is_plain(dice) => match (&dice.count, &dice.sides) {
+                (&Amount::Fixed(count), &Amount::Fixed(sides)) if sides >= 1 => {
+                    let kind = rolled_kind(dice.kind, sides, &RollOptions::default());
+                    let highest = i64::from(count.max(0)) * i64::from(max_face(sides, kind));
+                    Some(if dice.negative { (-highest, 0) } else { (0, highest) })
+                }
+                _ => None,
+            },
+            _ => None,
+        },
+        |(left_low, left_high), (right_low, right_high), subtract| {
+            Some(if subtract {
+                (left_low.saturating_sub(right_high), left_high.saturating_sub(right_low))
+            } else {
+                (left_low.saturating_add(right_low), left_high.saturating_add(right_high))
+            })
+        },
+    )?;
+    Some(range.1)
+}
+
+/// Writes a formula in RPN as infix notation, with its expression modifiers after it.
+fn write_notation(
+    rolls_formula: &[String],
+    half: Option<RoundingMode>,
+    dc: Option<i32>,
+    crit: Option<i32>,
+    fumble: Option<i32>,
+) -> String {
+    let mut notation = parse_into_infix_with(rolls_formula, BracketStyle::Round);
+    if rolls_formula.len() > 1 {
+        // The outermost operation needs no brackets.
+        notation = notation[1..notation.len() - 1].to_string();
+    }
+    match half {
+        Some(RoundingMode::Ceil) => notation.push_str(" halfup"),
+        Some(_) => notation.push_str(" half"),
+        None => {}
+    }
+    if let Some(dc) = dc {
+        notation.push_str(&format!(" dc{}", dc));
+    }
+    if let Some(crit) = crit {
+        notation.push_str(&format!(" crit{}", crit));
+    }
+    if let Some(fumble) = fumble {
+        notation.push_str(&format!(" fumble{}", fumble));
+    }
+    notation
+}
+
+/// Returns the provided formula written in a single canonical form, so that formulas
+/// written differently but rolled the same, such as `2d06+3` and `2d6 + 3`, can be
+/// compared, as when removing duplicate saved macros. Canonicalizing a formula twice
+/// gives the same form as canonicalizing it once.
+///
+/// In the canonical form:
+///
+/// * Dice are written with a lowercase `d` and always have a count, as in `1d6` for `D6`,
+///   and numbers have no leading zeros
+///   or grouped thousands, though `d00` is kept as it is.
+/// * Each operator is written in ASCII with a single space either side, as in `2d6 * 2`
+///   for `2d6×2`, and terms and modifiers have no spaces within them.
+/// * Every operation but the outermost is bracketed, as in `1d4 + (2 * 3)`.
+/// * Sets separate their terms with `, `, and conditionals separate their parts with ` ? `
+///   and ` : `, bracketing any other conditionals within them, and are themselves
+///   bracketed where they are a term of a larger formula.
+/// * Expression modifiers come after the formula, in the order `half` or `halfup`, `dc`,
+///   `crit` and then `fumble`, with `vs` written as `dc`.
+///
+/// # Arguments
+/// * `input` - A string that provides the dice notation to work off.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(Ok("1d6 + (2d6 * 2)".to_string()), rfyl::canonicalize("d06+2d6×2"));
+/// assert_eq!(Ok("1d20 + 5 dc15".to_string()), rfyl::canonicalize("1d20+5 VS 15"));
+/// ```
+pub fn canonicalize(input: &str) -> Result<String, RollError> {
+    let expression = parse_expression(input)?;
+    let formula = expression.get_formula();
+    let mut canonical: Vec<String> = Vec::new();
+    for (element, &position) in formula.iter().zip(expression.get_positions()) {
+        if let Some(operator) = Operator::from_symbol(element) {
+            canonical.push(operator.symbol().to_string());
+        } else {
+            let term = canonical_term(element).map_err(|e| e.at(position))?;
+            canonical.push(if formula.len() > 1 { bracket_conditional(term) } else { term });
+        }
+    }
+    Ok(write_notation(
+        &canonical,
+        expression.get_half(),
+        expression.get_dc(),
+        expression.get_crit(),
+        expression.get_fumble(),
+    ))
+}
+
+/// Writes a single term of a formula in its canonical form. See [canonicalize()](fn.canonicalize.html).
+fn canonical_term(term: &str) -> Result<String, RollError> {
+    let (body, tag) = split_tag(term);
+    let mut canonical = match parse_fragment(body)? {
+        Fragment::Constant(constant) => constant.to_string(),
+        Fragment::Repeat(repeat) => format!("{}({})", repeat.times, canonicalize(&repeat.expression)?),
+        Fragment::Conditional(conditional) => format!(
+            "{} ? {} : {}",
+            bracket_conditional(canonicalize(&conditional.condition)?),
+            bracket_conditional(canonicalize(&conditional.then)?),
+            bracket_conditional(canonicalize(&conditional.otherwise)?)
+        ),
+        Fragment::Dice(_) | Fragment::Set(_) => {
+            let mut canonical = String::new();
+            let mut rest = body;
+            let mut written_dice = false;
+            while let Some(c) = rest.chars().next() {
+                let taken = if c == '{' {
+                    let close = rest.find('}').unwrap_or(rest.len() - 1);
+                    let terms: Vec<String> = rest[1..close].split(',').map(canonical_term).collect::<Result<_, _>>()?;
+                    canonical.push_str(&format!("{{{}}}", terms.join(", ")));
+                    close + 1
+                } else if c == '(' {
+                    let close = find_closing_bracket(rest).unwrap_or(rest.len() - 1);
+                    canonical.push_str(&format!("({})", canonicalize(&rest[1..close])?));
+                    close + 1
+                } else if c.is_ascii_digit() {
+                    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+                    let number = match rest[..digits].trim_start_matches('0') {
+                        // The sides of a `d00` are kept as they are, and any other zeros are one.
+                        "" if written_dice && canonical.ends_with('d') => &rest[..digits],
+                        "" => "0",
+                        number => number,
+                    };
+                    canonical.push_str(number);
+                    digits
+                } else if (c == 'd' || c == 'D') && !written_dice {
+                    if canonical.is_empty() || canonical == "-" {
+                        canonical.push('1');
+                    }
+                    canonical.push('d');
+                    written_dice = true;
+                    1
+                } else {
+                    canonical.push(c);
+                    c.len_utf8()
+                };
+                rest = &rest[taken..];
+            }
+            canonical
+        }
+    };
+    if let Some(tag) = tag {
+        canonical.push_str(&format!("[{}]", tag));
+    }
+    Ok(canonical)
+}
+
+/// Brackets a canonical formula that is a conditional, so that it can be written within another.
+fn bracket_conditional(formula: String) -> String {
+    if find_top_level(&formula, '?').is_some() {
+        format!("({})", formula)
+    } else {
+        formula
+    }
+}
+
+fn roll_with_roller<R: DieRoller>(input: &str, options: &RollOptions, rng: &mut R) -> Result<DiceRolls, RollError> {
+    evaluate_with_roller(&parse_expression(input)?, options, rng)
+}
+
+/// Rolls an expression given to one of the public functions, within the operations
+/// allowed by `options`.
+fn evaluate_with_roller<R: DieRoller>(
+    expression: &ParsedExpression,
+    options: &RollOptions,
+    rng: &mut R,
+) -> Result<DiceRolls, RollError> {
+    let mut limited = OperationLimit::new(rng, options.max_operations);
+    let mut recorded = RecordFaces::new(&mut limited);
+    let rolls = resolve_expression(expression, options, &mut recorded)?;
+    Ok(DiceRolls { faces: recorded.faces, ..rolls })
+}
+
+/// Rolls an expression, including those bracketed within another. Nested expressions are
+/// rolled with the same roller, so that they count towards the same operation limit.
+fn resolve_expression<R: DieRoller>(
+    expression: &ParsedExpression,
+    options: &RollOptions,
+    rng: &mut R,
+) -> Result<DiceRolls, RollError> {
+    validate_rpn_formula(expression.get_formula())?;
+    let rolls = resolve_rolls_vector(
+        expression.get_formula().to_vec(),
+        expression.get_positions(),
+        options,
+        rng,
+    )?;
+    Ok(DiceRolls {
+        dc: expression.get_dc(),
+        half: expression.get_half(),
+        crit: expression.get_crit(),
+        fumble: expression.get_fumble(),
+        ..rolls
+    })
+}
+
+/// Rolls each term of a postfix formula. `positions` gives the character offset of each
+/// term in the input, for errors; terms without one are taken to be at offset 0.
+fn resolve_rolls_vector<R: DieRoller>(
+    rolls_vector: Vec<String>,
+    positions: &[usize],
+    options: &RollOptions,
+    rng: &mut R,
+) -> Result<DiceRolls, RollError> {
+    let mut formula_vector: Vec<String> = Vec::new();
+    let mut formula_vector_with_rolls: Vec<String> = Vec::new();
+    let mut dice_rolls: Vec<DiceRoll> = Vec::new();
+    let mut pool: Option<PoolResult> = None;
+    let mut groups: Vec<GroupSpan> = Vec::new();
+
+    for (index, element) in rolls_vector.into_iter().enumerate() {
+        // Ignore if element is recognised as a token.
+        if match_token(element.as_ref()) > 0 {
+            formula_vector.push(element.clone());
+            formula_vector_with_rolls.push(element);
+            continue;
+        }
+
+        let position = positions.get(index).cloned().unwrap_or(0);
+        let roll = resolve_roll_fragment(element.as_ref(), position, options, rng)?;
+
+        for i_roll in roll.clone().rolls {
+            dice_rolls.push(i_roll);
+        }
+
+        if let Some(fragment_pool) = roll.pool {
+            pool = Some(pool.unwrap_or_default().combine(fragment_pool));
+        }
+
+        formula_vector.extend(roll.formula);
+        formula_vector_with_rolls.push(element);
+        groups.extend(roll.groups);
+    }
+
+    Ok(DiceRolls {
+        rolls: dice_rolls,
+        formula: formula_vector,
+        rolls_formula: formula_vector_with_rolls,
+        pool,
+        dc: None,
+        half: None,
+        crit: None,
+        fumble: None,
+        faces: Vec::new(),
+        groups,
+        options: options.clone(),
+    })
+}
+
+/// Rolls a single fragment, applying its modifiers in a fixed order:
+///
+/// 1. Each die is rolled, and rerolled for as long as it matches the reroll target (`r`),
+///    or rerolled once if it matches, keeping the new roll (`rh`) or the higher (`rk`).
+///    Rerolled dice are kept for display but no longer count.
+/// 2. Each die, after rerolling, explodes (`!`) or penetrates (`p`) on its maximum face.
+///    Dice added this way are not themselves rerolled.
+/// 3. Each die has the per-die modifier (`e`) added, then counts for no more than the cap
+///    (`cap`) and no less than the floor (`floor`).
+/// 4. The highest or lowest dice, including any added by explosions, are kept or
+///    dropped (`kh`, `kl`, `dh`, `dl`).
+/// 5. The remaining dice are counted against the success and failure targets.
+///
+/// A set of terms in braces rolls each term this way, then keeps or drops from all of
+/// their remaining dice together. A repeated formula is rolled in full each time, and
+/// counts as the total of its results.
+fn resolve_roll_fragment<R: DieRoller>(
+    input_fragment: &str,
+    position: usize,
+    options: &RollOptions,
+    rng: &mut R,
+) -> Result<DiceRolls, RollError> {
+    // Bracketed counts and sides are rolled first, and their dice are kept
+    // for display but don't count towards this fragment's own sum or modifiers.
+    let mut amount_rolls: Vec<DiceRoll> = Vec::new();
+    let mut dice_rolls: Vec<DiceRoll> = Vec::new();
+    let mut pool: Option<PoolResult> = None;
+    let mut formula_total: Option<i32> = None;
+
+    match parse_fragment(input_fragment).map_err(|e| e.at(position))? {
+        Fragment::Constant(constant) => {
+            dice_rolls.push(DiceRoll {
+                sides: 0,
+                highest: 0,
+                result: constant,
+                natural: constant,
+                kept: true,
+                percentile: false,
+                negative: false,
+            });
+        }
+        Fragment::Dice(dice) => {
+            dice_rolls = roll_dice(&dice, input_fragment, position, options, rng, &mut amount_rolls)?;
+
+            if let Some(rules) = dice.pool {
+                let kept = || dice_rolls.iter().filter(|r| r.kept);
+                let result = PoolResult::new(
+                    kept().map(|r| rules.successes(r.highest, r.result)).sum(),
+                    kept().map(|r| rules.failures(r.result)).sum(),
+                );
+                if options.count_pool_successes {
+                    formula_total = Some(result.net);
+                }
+                pool = Some(result);
+            }
+        }
+        Fragment::Set(set) => {
+            for term in set.terms {
+                match term {
+                    Fragment::Constant(constant) => dice_rolls.push(DiceRoll {
+                        sides: 0,
+                        highest: 0,
+                        result: constant,
+                        natural: constant,
+                        kept: true,
+                        percentile: false,
+                        negative: false,
+                    }),
+                    Fragment::Dice(dice) => {
+                        let term_rolls =
+                            roll_dice(&dice, input_fragment, position, options, rng, &mut amount_rolls)?;
+                        dice_rolls.extend(term_rolls);
+                    }
+                    Fragment::Set(_) | Fragment::Repeat(_) | Fragment::Conditional(_) => {
+                        return Err(RollError::InvalidFragment {
+                            fragment: input_fragment.to_string(),
+                            position,
+                        })
+                    }
+                }
+            }
+
+            if let Some(selection) = set.selection {
+                apply_selection(&mut dice_rolls, selection);
+            }
+        }
+        Fragment::Repeat(repeat) => {
+            // Each repetition's dice are kept for display, with the total of its formula
+            // added to the sum in place of the dice themselves.
+            let mut total: i32 = 0;
+            let expression = parse_expression(&repeat.expression).map_err(|e| e.at(position))?;
+            for _ in 0..repeat.times.abs() {
+                rng.take_operation()?;
+                let inner = resolve_expression(&expression, options, rng).map_err(|e| e.at(position))?;
+                total = total.checked_add(inner.get_result_checked()?).ok_or(RollError::Overflow)?;
+                if let Some(inner_pool) = inner.pool {
+                    pool = Some(pool.unwrap_or_default().combine(inner_pool));
+                }
+                amount_rolls.extend(inner.rolls);
+            }
+            if repeat.times < 0 {
+                total = total.checked_neg().ok_or(RollError::Overflow)?;
+            }
+            formula_total = Some(total);
+        }
+        Fragment::Conditional(conditional) => {
+            // The condition's dice are kept for display, and only the branch it chose is
+            // rolled, counting for the total of its formula. The condition is read from its
+            // total as rolled, before any halving or `floor_result_at`.
+            let condition = roll_formula(&conditional.condition, position, options, rng)?;
+            let met = match condition.get_successes() {
+                Some(successes) => successes > 0,
+                None => condition.raw_total()? != 0,
+            };
+            amount_rolls.extend(condition.rolls);
+            let branch = if met { &conditional.then } else { &conditional.otherwise };
+            let inner = roll_formula(branch, position, options, rng)?;
+            pool = inner.pool;
+            formula_total = Some(inner.get_result_checked()?);
+            amount_rolls.extend(inner.rolls);
+        }
+    }
+
+    let sum: i32 = match formula_total {
+        Some(total) => total,
+        None => dice_rolls
+            .iter()
+            .filter(|r| r.kept)
+            .try_fold(0i32, |sum, r| sum.checked_add(r.result))
+            .ok_or(RollError::Overflow)?,
+    };
+    amount_rolls.extend(dice_rolls);
+    Ok(DiceRolls {
+        groups: vec![GroupSpan {
+            notation: input_fragment.to_string(),
+            len: amount_rolls.len(),
+            subtotal: sum,
+            tag: split_tag(input_fragment).1.map(|tag| tag.to_string()),
+        }],
+        rolls: amount_rolls,
+        formula: vec![sum.to_string()],
+        rolls_formula: vec![input_fragment.to_string()],
+        pool,
+        dc: None,
+        half: None,
+        crit: None,
+        fumble: None,
+        faces: Vec::new(),
+        options: options.clone(),
+    })
+}
+
+/// Rolls a formula written within a fragment, with any errors pointing at the fragment.
+fn roll_formula<R: DieRoller>(
+    formula: &str,
+    position: usize,
+    options: &RollOptions,
+    rng: &mut R,
+) -> Result<DiceRolls, RollError> {
+    let expression = parse_expression(formula).map_err(|e| e.at(position))?;
+    resolve_expression(&expression, options, rng).map_err(|e| e.at(position))
+}
+
+/// Rolls the dice of a single dice fragment, applying its rerolls, explosions and
+/// selection. Any dice rolled for a bracketed count or sides are added to `amount_rolls`.
+/// Errors point at `position`, including those from within a bracketed count or sides.
+fn roll_dice<R: DieRoller>(
+    dice: &DiceFragment,
+    input_fragment: &str,
+    position: usize,
+    options: &RollOptions,
+    rng: &mut R,
+    amount_rolls: &mut Vec<DiceRoll>,
+) -> Result<Vec<DiceRoll>, RollError> {
+    let mut dice_rolls: Vec<DiceRoll> = Vec::new();
+    let dice_count =
+        resolve_amount(&dice.count, input_fragment, options, rng, amount_rolls).map_err(|e| e.at(position))?;
+    let dice_sides =
+        resolve_amount(&dice.sides, input_fragment, options, rng, amount_rolls).map_err(|e| e.at(position))?;
+    // A die needs at least one side to be rolled, however its sides were given.
+    if dice_sides < 1 {
+        return Err(RollError::InvalidDiceSides {
+            fragment: input_fragment.to_string(),
+            position,
+        });
+    }
+
+    let breakdown = dice.kind == DieKind::Percentile && options.percentile_breakdown;
+    let kind = rolled_kind(dice.kind, dice_sides, options);
+    let highest = max_face(dice_sides, kind);
+    let fixed_one = dice_sides == 1 && kind == DieKind::Standard && !options.boolean_dice;
+    let roll = |rng: &mut R| -> Result<i32, RollError> {
+        rng.take_operation()?;
+        if breakdown {
+            // The tens and ones dice show `00` and `0` together for a hundred.
+            let tens = rng.roll_die(10, DieKind::Tens);
+            let ones = rng.roll_die(10, DieKind::Standard) % 10;
+            return Ok(if tens + ones == 0 { 100 } else { tens + ones });
+        }
+        Ok(if fixed_one { 1 } else { rng.roll_die(dice_sides, kind) })
+    };
+
+    // A count of zero or less rolls no dice.
+    for _ in 0..dice_count {
+        let mut natural = roll(rng)?;
+
+        let rerolled = |natural: i32| DiceRoll {
+            sides: dice_sides,
+            highest,
+            result: natural,
+            natural,
+            kept: false,
+            percentile: breakdown,
+            negative: false,
+        };
+        match dice.reroll {
+            Some(Reroll { target, rule: RerollRule::Until }) => {
+                let mut depth = 0;
+                while target.matches(natural) && depth < MAX_REROLL_DEPTH {
+                    dice_rolls.push(rerolled(natural));
+                    natural = roll(rng)?;
+                    depth += 1;
+                }
+            }
+            Some(Reroll { target, rule }) if target.matches(natural) => {
+                // The roll that isn't kept is shown first, whichever was rolled first.
+                let again = roll(rng)?;
+                if rule == RerollRule::KeepHigher && natural > again {
+                    dice_rolls.push(rerolled(again));
+                } else {
+                    dice_rolls.push(rerolled(natural));
+                    natural = again;
+                }
+            }
+            _ => {}
+        }
+
+        dice_rolls.push(DiceRoll {
+            sides: dice_sides,
+            highest,
+            result: natural,
+            natural,
+            kept: true,
+            percentile: breakdown,
+            negative: false,
+        });
+
+        // Exploding dice roll again on a maximum, or on any face passing their threshold,
+        // and penetrating dice do the same with each follow-up die counting one less than it shows.
+        if let Some(explosion) = dice.explosion {
+            let mut depth = 0;
+            let mut added = 0;
+            let below_total = |added: i32| options.max_explosion_total.map_or(true, |total| added < total);
+            let explodes = |natural: i32| dice.explode_on.map_or(natural == highest, |on| on.matches(natural));
+            while explodes(natural) && depth < options.max_explosion_depth && below_total(added) {
+                natural = roll(rng)?;
+                let mut result = match explosion {
+                    Explosion::Standard => natural,
+                    Explosion::Penetrating => natural - 1,
+                };
+                if let Some(total) = options.max_explosion_total {
+                    result = result.min(total - added);
+                }
+                dice_rolls.push(DiceRoll {
+                    sides: dice_sides,
+                    highest,
+                    result,
+                    natural,
+                    kept: true,
+                    percentile: breakdown,
+                    negative: false,
+                });
+                added = added.saturating_add(result);
+                depth += 1;
+            }
+        }
+    }
+
+    // Per-die modifiers, caps and floors change what each die counts for, after any
+    // explosions have been triggered by the faces the dice showed.
+    for die in dice_rolls.iter_mut() {
+        if let Some(each) = dice.each {
+            die.result = die.result.checked_add(each).ok_or(RollError::Overflow)?;
+        }
+        if let Some(cap) = dice.cap {
+            die.result = die.result.min(cap);
+        }
+        if let Some(floor) = dice.floor {
+            die.result = die.result.max(floor);
+        }
+    }
+
+    if let Some(selection) = dice.selection {
+        apply_selection(&mut dice_rolls, selection);
+    }
+
+    // Negative dice are kept or dropped by the faces they show, and then count against the total.
+    if dice.negative {
+        for die in dice_rolls.iter_mut() {
+            die.result = -die.result;
+            die.negative = true;
+        }
+    }
+
+    Ok(dice_rolls)
+}
+
+fn resolve_amount<R: DieRoller>(
+    amount: &Amount,
+    input_fragment: &str,
+    options: &RollOptions,
+    rng: &mut R,
+    dice_rolls: &mut Vec<DiceRoll>,
+) -> Result<i32, RollError> {
+    match *amount {
+        Amount::Fixed(value) => Ok(value),
+        Amount::Expression(ref expression) => {
+            let inner = resolve_expression(&parse_expression(expression)?, options, rng)?;
+            dice_rolls.extend(inner.rolls.iter().cloned());
+            inner.get_result_checked()
+        }
+        // Sides can only be left out when the options give a default.
+        Amount::Default => options.default_sides.ok_or_else(|| RollError::InvalidFragment {
+            fragment: input_fragment.to_string(),
+            position: 0,
+        }),
+    }
+}
+
+/// Marks the dice that are dropped by a keep or drop modifier as no longer kept.
+/// Dice that were already rerolled away are ignored, and ties are broken in roll order.
+fn apply_selection(dice_rolls: &mut [DiceRoll], selection: Selection) {
+    let mut kept: Vec<usize> = (0..dice_rolls.len()).filter(|&i| dice_rolls[i].kept).collect();
+    kept.sort_by_key(|&i| dice_rolls[i].result);
+
+    let total = kept.len();
+    let clamp = |n: i32| (n.max(0) as usize).min(total);
+    let dropped: &[usize] = match selection {
+        Selection::KeepHighest(n) => &kept[..total - clamp(n)],
+        Selection::KeepLowest(n) => &kept[clamp(n)..],
+        Selection::DropHighest(n) => &kept[total - clamp(n)..],
+        Selection::DropLowest(n) => &kept[..clamp(n)],
+    };
+
+    for &i in dropped {
+        dice_rolls[i].kept = false;
+    }
+}
+
+/// Returns the given text as a quoted JSON string.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Returns the kind of die that is rolled for dice of the given kind and sides, which is
+/// zero-indexed for standard and percentile dice with the `zero_indexed_dice` option.
+fn rolled_kind(kind: DieKind, sides: i32, options: &RollOptions) -> DieKind {
+    let breakdown = kind == DieKind::Percentile && options.percentile_breakdown;
+    let zero_indexed = options.zero_indexed_dice
+        && sides > 1
+        && !breakdown
+        && (kind == DieKind::Standard || kind == DieKind::Percentile);
+    if zero_indexed {
+        DieKind::ZeroIndexed
+    } else {
+        kind
+    }
+}
+
+/// Returns every face of a die, each as likely as the others to be rolled.
+fn die_faces(sides: i32, kind: DieKind, options: &RollOptions) -> Vec<i32> {
+    match kind {
+        DieKind::Average => AVERAGE_DIE_FACES.to_vec(),
+        DieKind::Tens => (0..10).map(|face| face * 10).collect(),
+        DieKind::Digits => sides.to_string().chars().fold(vec![0], |numbers, _| {
+            numbers.iter().flat_map(|number| (1..7).map(move |digit| number * 10 + digit)).collect()
+        }),
+        DieKind::ZeroIndexed => (0..sides).collect(),
+        _ if sides == 1 && options.boolean_dice => vec![0, 1],
+        _ => (1..sides + 1).collect(),
+    }
+}
+
+/// The most steps that working out the distribution of a formula's total may take, where
+/// each step adds a single face of a die to a single possible total.
+const MAX_DISTRIBUTION_STEPS: usize = 1_000_000;
+
+/// Returns the number of dice a term of a formula rolls and the faces of each, with a
+/// constant read as a single die with one face, for terms without any dice modifiers.
+/// Negative dice have their faces negated. Any other term gives None.
+fn plain_term_faces(term: &str, options: &RollOptions) -> Option<(i32, Vec<i32>)> {
+    let dice = match parse_fragment(term).ok()? {
+        Fragment::Constant(constant) => return Some((1, vec![constant])),
+        Fragment::Dice(dice) => dice,
+        _ => return None,
+    };
+    let (count, sides) = match (&dice.count, &dice.sides) {
+        (&Amount::Fixed(count), &Amount::Fixed(sides)) => (count, sides),
+        (&Amount::Fixed(count), &Amount::Default) => (count, options.default_sides?),
+        _ => return None,
+    };
+    if !is_plain(&dice) || sides < 1 {
+        return None;
+    }
+    let sign = if dice.negative { -1 } else { 1 };
+    let faces = die_faces(sides, rolled_kind(dice.kind, sides, options), options);
+    Some((count, faces.into_iter().map(|face| sign * face).collect()))
+}
+
+/// Returns whether dice are rolled without any modifiers, so each counts for the face it shows.
+fn is_plain(dice: &DiceFragment) -> bool {
+    dice.reroll.is_none()
+        && dice.explosion.is_none()
+        && dice.each.is_none()
+        && dice.cap.is_none()
+        && dice.floor.is_none()
+        && dice.selection.is_none()
+        && dice.pool.is_none()
+}
+
+/// Folds a formula in RPN that only adds and subtracts terms, reading each term with
+/// `term` and joining the values of two parts with `join`, which is given whether the
+/// second part is subtracted. Any other formula, or any term that `term` can't read, gives None.
+fn fold_additive<T, F, J>(rolls_formula: &[String], mut term: F, mut join: J) -> Option<T>
+where
+    F: FnMut(&str) -> Option<T>,
+    J: FnMut(T, T, bool) -> Option<T>,
+{
+    let mut stack: Vec<T> = Vec::new();
+    for element in rolls_formula {
+        let precedence = match_token(element);
+        if precedence == 1 || precedence == 2 {
+            let right = stack.pop()?;
+            let left = stack.pop()?;
+            stack.push(join(left, right, precedence == 1)?);
+        } else if precedence > 0 {
+            return None;
+        } else {
+            stack.push(term(element)?);
+        }
+    }
+
+    if stack.len() == 1 {
+        stack.pop()
+    } else {
+        None
+    }
+}
+
+/// Returns the probability of each total of a formula in RPN that only adds and subtracts
+/// constants and dice without modifiers, or None for any other formula.
+fn total_distribution(rolls_formula: &[String], options: &RollOptions) -> Option<BTreeMap<i32, f64>> {
+    let mut steps = 0;
+    fold_additive(
+        rolls_formula,
+        |term| {
+            let (count, faces) = plain_term_faces(term, options)?;
+            let chance = 1.0 / faces.len() as f64;
+            let mut distribution = BTreeMap::new();
+            distribution.insert(0, 1.0);
+            for _ in 0..count {
+                steps += distribution.len() * faces.len();
+                if steps > MAX_DISTRIBUTION_STEPS {
+                    return None;
+                }
+                let mut next = BTreeMap::new();
+                for (total, p) in &distribution {
+                    for face in &faces {
+                        *next.entry(total + face).or_insert(0.0) += p * chance;
+                    }
+                }
+                distribution = next;
+            }
+            Some(distribution)
+        },
+        |left: BTreeMap<i32, f64>, right: BTreeMap<i32, f64>, subtract| {
+            let sign = if subtract { -1 } else { 1 };
+            let mut combined = BTreeMap::new();
+            for (a, p) in &left {
+                for (b, q) in &right {
+                    *combined.entry(a + sign * b).or_insert(0.0) += p * q;
+                }
+            }
+            Some(combined)
+        },
+    )
+}
+
+/// Returns the square root of a positive number, found by Newton's method as `f64::sqrt`
+/// needs the standard library.
+fn sqrt(value: f64) -> f64 {
+    let mut root = if value > 1.0 { value } else { 1.0 };
+    loop {
+        let next = (root + value / root) / 2.0;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
+/// Returns the mean and variance of the total of a formula in RPN that only adds and
+/// subtracts constants and dice without modifiers, or None for any other formula.
+fn total_moments(rolls_formula: &[String], options: &RollOptions) -> Option<(f64, f64)> {
+    fold_additive(
+        rolls_formula,
+        |term| {
+            let (count, faces) = plain_term_faces(term, options)?;
+            let sides = faces.len() as f64;
+            let mean = faces.iter().map(|&face| f64::from(face)).sum::<f64>() / sides;
+            let squares: f64 = faces.iter().map(|&face| (f64::from(face) - mean) * (f64::from(face) - mean)).sum();
+            let variance = squares / sides;
+            let count = f64::from(count.max(0));
+            Some((count * mean, count * variance))
+        },
+        |(left_mean, left_variance), (right_mean, right_variance), subtract| {
+            let mean = if subtract { left_mean - right_mean } else { left_mean + right_mean };
+            Some((mean, left_variance + right_variance))
+        },
+    )
+}
+
+/// Returns the highest face of a die, which is what explodes and what counts double.
+fn max_face(sides: i32, kind: DieKind) -> i32 {
+    match kind {
+        DieKind::Standard => sides,
+        DieKind::Average => AVERAGE_DIE_FACES[AVERAGE_DIE_FACES.len() - 1],
+        DieKind::Tens => 90,
+        DieKind::Digits => sides,
+        DieKind::Percentile => sides,
+        DieKind::ZeroIndexed => sides - 1,
+    }
+}
+
+/// Returns whether a die could have shown `face`, as rolled by a random number generator.
+fn can_show(sides: i32, kind: DieKind, face: i32) -> bool {
+    match kind {
+        DieKind::Average => AVERAGE_DIE_FACES.contains(&face),
+        DieKind::Tens => (0..=90).contains(&face) && face % 10 == 0,
+        DieKind::Digits => {
+            let digits = face.to_string();
+            digits.len() == sides.to_string().len() && digits.chars().all(|digit| ('1'..='6').contains(&digit))
+        }
+        DieKind::ZeroIndexed => (0..sides).contains(&face),
+        _ if sides == 1 => face == 0 || face == 1,
+        _ => (1..=sides).contains(&face),
+    }
+}
+
+/// A source of die faces, which is usually a random number generator.
+trait DieRoller {
+    fn roll_die(&mut self, sides: i32, kind: DieKind) -> i32;
+
+    /// Counts an operation towards any limit on the operations of a roll, returning
+    /// `RollError::ComputationLimit` once the limit is reached.
+    fn take_operation(&mut self) -> Result<(), RollError> {
+        Ok(())
+    }
+}
+
+/// Rolls with another roller, allowing only so many operations.
+struct OperationLimit<'a, R: 'a> {
+    roller: &'a mut R,
+    remaining: u32,
+}
+
+impl<'a, R: DieRoller> OperationLimit<'a, R> {
+    fn new(roller: &'a mut R, limit: u32) -> OperationLimit<'a, R> {
+        OperationLimit { roller, remaining: limit }
+    }
+}
+
+impl<'a, R: DieRoller> DieRoller for OperationLimit<'a, R> {
+    fn roll_die(&mut self, sides: i32, kind: DieKind) -> i32 {
+        self.roller.roll_die(sides, kind)
+    }
+
+    fn take_operation(&mut self) -> Result<(), RollError> {
+        self.roller.take_operation()?;
+        if self.remaining == 0 {
+            return Err(RollError::ComputationLimit);
+        }
+        self.remaining -= 1;
+        Ok(())
+    }
+}
+
+/// Rolls with another roller, keeping each face it rolls.
+struct RecordFaces<'a, R: 'a> {
+    roller: &'a mut R,
+    faces: Vec<i32>,
+}
+
+impl<'a, R: DieRoller> RecordFaces<'a, R> {
+    fn new(roller: &'a mut R) -> RecordFaces<'a, R> {
+        RecordFaces { roller, faces: Vec::new() }
+    }
+}
+
+impl<'a, R: DieRoller> DieRoller for RecordFaces<'a, R> {
+    fn roll_die(&mut self, sides: i32, kind: DieKind) -> i32 {
+        let face = self.roller.roll_die(sides, kind);
+        self.faces.push(face);
+        face
+    }
+
+    fn take_operation(&mut self) -> Result<(), RollError> {
+        self.roller.take_operation()
+    }
+}
+
+/// Rolls the given faces in order, noting whether any more were asked for.
+struct ReplayFaces<'a> {
+    faces: alloc::slice::Iter<'a, (i32, usize)>,
+    exhausted: bool,
+    /// The first face, and its position, that its die couldn't have shown.
+    impossible: Option<(i32, usize)>,
+}
+
+impl<'a> DieRoller for ReplayFaces<'a> {
+    fn roll_die(&mut self, sides: i32, kind: DieKind) -> i32 {
+        match self.faces.next() {
+            Some(&(face, position)) => {
+                if self.impossible.is_none() && !can_show(sides, kind, face) {
+                    self.impossible = Some((face, position));
+                }
+                face
+            }
+            None => {
+                self.exhausted = true;
+                0
+            }
+        }
+    }
+}
+
+impl<R: Rng> DieRoller for R {
+    fn roll_die(&mut self, sides: i32, kind: DieKind) -> i32 {
+        // gen_range(low, high) generates numbers in the range [low, high),
+        // so the high number must be one higher than the highest number
+        // that would appear on the die
+        if kind == DieKind::Average {
+            // Average dice pick one of their six faces
+            AVERAGE_DIE_FACES[self.gen_range(0, AVERAGE_DIE_FACES.len())]
+        } else if kind == DieKind::Tens {
+            // Percentile tens dice show a multiple of ten from 00 to 90
+            self.gen_range(0, 10) * 10
+        } else if kind == DieKind::Digits {
+            // A d6 is rolled for each digit of the sides, e.g. two for a d66
+            sides.to_string().chars().fold(0, |number, _| number * 10 + self.gen_range(1, 7))
+        } else if kind == DieKind::ZeroIndexed {
+            // Zero-indexed dice start from 0 rather than 1
+            self.gen_range(0, sides)
+        } else if sides == 1 {
+            // Support "one sided" boolean dice
+            self.gen_range(0, 2)
+        } else {
+            // Support multi-sided dice, working out the bound in a wider type so that a die
+            // with as many sides as an i32 can hold can still be rolled
+            self.gen_range(1, i64::from(sides) + 1) as i32
+        }
+    }
+}
+
+/// Gives each die its average face, rounding averages between two faces down and up in turn.
+struct AverageFaces {
+    round_up: bool,
+}
+
+impl DieRoller for AverageFaces {
+    fn roll_die(&mut self, sides: i32, kind: DieKind) -> i32 {
+        // Each kind of die rolls its faces evenly, so its average is halfway between
+        // its lowest and highest faces.
+        let (lowest, highest) = match kind {
+            DieKind::Average => (AVERAGE_DIE_FACES[0], AVERAGE_DIE_FACES[AVERAGE_DIE_FACES.len() - 1]),
+            DieKind::Tens => (0, 90),
+            DieKind::Digits => sides.to_string().chars().fold((0, 0), |(low, high), _| (low * 10 + 1, high * 10 + 6)),
+            DieKind::ZeroIndexed => (0, sides - 1),
+            _ if sides == 1 => (0, 1),
+            _ => (1, sides),
+        };
+        let twice = lowest + highest;
+        if twice % 2 == 0 {
+            return twice / 2;
+        }
+        let average = if self.round_up { twice / 2 + 1 } else { twice / 2 };
+        self.round_up = !self.round_up;
+        average
+    }
+}
+
+/// Gives each die the next of a list of faces, then rolls randomly once they run out.
+#[cfg(feature = "std")]
+struct ForcedRolls<'a, R> {
+    forced: alloc::slice::Iter<'a, i32>,
+    fallback: R,
+}
+
+#[cfg(feature = "std")]
+impl<'a, R: Rng> DieRoller for ForcedRolls<'a, R> {
+    fn roll_die(&mut self, sides: i32, kind: DieKind) -> i32 {
+        match self.forced.next() {
+            Some(&face) => face,
+            None => self.fallback.roll_die(sides, kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use rand::rngs::StdRng;
+    #[cfg(not(feature = "std"))]
+    use rand::SeedableRng;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_from_string() {
+        println!();
+        let roll0 = roll("2d4".to_string()).unwrap();
+        println!("Rolls:             {}", roll0.get_rolls_string());
+        println!("RPN Formula:       {}", roll0.get_formula_string_as_rpn());
+        println!("Formula:           {}", roll0.get_formula_string_as_infix());
+        println!("RPN Rolls Formula: {}", roll0.get_rolls_formula_string_as_rpn());
+        println!("Rolls Formula:     {}", roll0.get_rolls_formula_string_as_infix());
+        println!("Result:            {}", roll0.get_result());
+        println!();
+
+        let roll1 = roll("(2d6 - 1d8) * (3d4 + 4d12)".to_string()).unwrap();
+        println!("Rolls:             {}", roll1.get_rolls_string());
+        println!("RPN Formula:       {}", roll1.get_formula_string_as_rpn());
+        println!("Formula:           {}", roll1.get_formula_string_as_infix());
+        println!("RPN Rolls Formula: {}", roll1.get_rolls_formula_string_as_rpn());
+        println!("Rolls Formula:     {}", roll1.get_rolls_formula_string_as_infix());
+        println!("Result:            {}", roll1.get_result());
+        println!();
+
+        let roll2 = roll("3d% + d%".to_string()).unwrap();
+        println!("Rolls:             {}", roll2.get_rolls_string());
+        println!("RPN Formula:       {}", roll2.get_formula_string_as_rpn());
+        println!("Formula:           {}", roll2.get_formula_string_as_infix());
+        println!("RPN Rolls Formula: {}", roll2.get_rolls_formula_string_as_rpn());
+        println!("Rolls Formula:     {}", roll2.get_rolls_formula_string_as_infix());
+        println!("Result:            {}", roll2.get_result());
+        println!();
+
+        let roll3 = roll("d100 / 15".to_string()).unwrap();
+        println!("Rolls:             {}", roll3.get_rolls_string());
+        println!("RPN Formula:       {}", roll3.get_formula_string_as_rpn());
         println!("Formula:           {}", roll3.get_formula_string_as_infix());
         println!("RPN Rolls Formula: {}", roll3.get_rolls_formula_string_as_rpn());
         println!("Rolls Formula:     {}", roll3.get_rolls_formula_string_as_infix());
         println!("Result:            {}", roll3.get_result());
         println!();
 
-        let roll4 = roll("1d4 + 2d6 * 3d2 / 4d8 + (2d6 + 3d8) - 16 * (1 / 1d4)".to_string()).unwrap();
-        println!("Rolls:             {}", roll4.get_rolls_string());
-        println!("RPN Formula:       {}", roll4.get_formula_string_as_rpn());
-        println!("Formula:           {}", roll4.get_formula_string_as_infix());
-        println!("RPN Rolls Formula: {}", roll4.get_rolls_formula_string_as_rpn());
-        println!("Rolls Formula:     {}", roll4.get_rolls_formula_string_as_infix());
-        println!("Result:            {}", roll4.get_result());
-        println!();
+        let roll4 = roll("1d4 + 2d6 * 3d2 / 4d8 + (2d6 + 3d8) - 16 * (1 / 1d4)".to_string()).unwrap();
+        println!("Rolls:             {}", roll4.get_rolls_string());
+        println!("RPN Formula:       {}", roll4.get_formula_string_as_rpn());
+        println!("Formula:           {}", roll4.get_formula_string_as_infix());
+        println!("RPN Rolls Formula: {}", roll4.get_rolls_formula_string_as_rpn());
+        println!("Rolls Formula:     {}", roll4.get_rolls_formula_string_as_infix());
+        println!("Result:            {}", roll4.get_result());
+        println!();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn count_pool_successes() {
+        let plain = roll("6d10 + 2".to_string()).unwrap();
+        assert_eq!(None, plain.get_successes());
+
+        for _ in 0..100 {
+            let pool = roll("6d10>=7f1dbl".to_string()).unwrap();
+            let expected: i32 = pool.rolls.iter().map(|r| match r.result {
+                10 => 2,
+                7..=9 => 1,
+                1 => -1,
+                _ => 0,
+            }).sum();
+            assert_eq!(Some(expected), pool.get_successes());
+        }
+
+        let all = roll("4d6>=1".to_string()).unwrap();
+        assert_eq!(Some(4), all.get_successes());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn summarise_pools() {
+        assert_eq!(None, roll("6d10 + 2".to_string()).unwrap().get_pool_result());
+
+        for seed in 0..100 {
+            let rolls = roll_seeded("6d10>=7f1", seed);
+            let pool = rolls.get_pool_result().unwrap();
+            assert_eq!(rolls.rolls.iter().filter(|r| r.result >= 7).count() as i32, pool.successes);
+            assert_eq!(rolls.rolls.iter().filter(|r| r.result == 1).count() as i32, pool.failures);
+            assert_eq!(pool.successes - pool.failures, pool.net);
+            assert_eq!(pool.failures > 0 && pool.net <= 0, pool.botch);
+            assert_eq!(Some(pool.net), rolls.get_successes());
+        }
+
+        // Pools across several terms are added together.
+        let pool = roll("3d6>=1 + 2d6<=6f1".to_string()).unwrap().get_pool_result().unwrap();
+        assert_eq!(5, pool.successes);
+        assert!(!pool.botch);
+        let botch = roll("3d6>6f<=6".to_string()).unwrap().get_pool_result().unwrap();
+        assert_eq!(PoolResult { successes: 0, failures: 3, net: -3, botch: true }, botch);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn count_face_distribution() {
+        let roll = roll("10d6 + 4".to_string()).unwrap();
+        let distribution = roll.get_face_distribution();
+        assert_eq!(10, distribution.values().sum::<u32>());
+        assert!(distribution.keys().all(|face| *face >= 1 && *face <= 6));
+        for (face, count) in &distribution {
+            assert_eq!(*count as usize, roll.rolls.iter().filter(|r| r.sides == 6 && r.result == *face).count());
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn limit_explosions() {
+        let options = RollOptions { max_explosion_total: Some(5), ..RollOptions::default() };
+        for seed in 0..200 {
+            let options = RollOptions { seed: Some(seed), ..options.clone() };
+            let rolls = roll_with_options("1d2!".to_string(), &options).unwrap();
+            let (first, follow_ups) = rolls.rolls.split_first().unwrap();
+            assert!(follow_ups.len() <= 5);
+            assert!(follow_ups.iter().map(|r| r.result).sum::<i32>() <= 5);
+            assert!(rolls.get_result() <= 2 + 5);
+            if first.result == 1 {
+                assert!(follow_ups.is_empty());
+            }
+        }
+
+        // A one-sided die always explodes, so shows how far each chain is allowed to go.
+        let fixed = RollOptions { boolean_dice: false, ..RollOptions::default() };
+        let capped = |depth: i32, total: Option<i32>| RollOptions {
+            max_explosion_depth: depth,
+            max_explosion_total: total,
+            ..fixed.clone()
+        };
+        assert_eq!(4, roll_with_options("1d1!".to_string(), &capped(3, None)).unwrap().get_result());
+        assert_eq!(3, roll_with_options("1d1!".to_string(), &capped(100, Some(2))).unwrap().get_result());
+        assert_eq!(2 * 3, roll_with_options("2d1!".to_string(), &capped(100, Some(2))).unwrap().get_result());
+        assert_eq!(2, roll_with_options("2d1!".to_string(), &capped(100, Some(0))).unwrap().rolls.len());
+        assert_eq!(101, roll_with_options("1d1!".to_string(), &fixed).unwrap().get_result());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn penetrate_on_maximum() {
+        for _ in 0..200 {
+            let roll = roll("1d2p".to_string()).unwrap();
+            let (first, follow_ups) = roll.rolls.split_first().unwrap();
+            assert!(first.result == 1 || first.result == 2);
+            if first.result == 1 {
+                assert!(follow_ups.is_empty());
+                continue;
+            }
+
+            // Every follow-up die shows one less than its face, and only a
+            // follow-up that showed the maximum face is followed by another.
+            let (last, chain) = follow_ups.split_last().unwrap();
+            assert!(chain.iter().all(|r| r.result == 1));
+            assert!(last.result == 0 || follow_ups.len() == RollOptions::default().max_explosion_depth as usize);
+            assert_eq!(roll.get_sum_of_rolls(), 2 + chain.len() as i32 + last.result);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn iterate_over_dice() {
+        let roll = roll("3d6 + 2".to_string()).unwrap();
+        let mut count = 0;
+        for die in &roll {
+            count += 1;
+            assert!(die.sides == 6 || die == RollResult { sides: 0, result: 2 });
+        }
+        assert_eq!(4, count);
+        assert_eq!(roll.get_sum_of_rolls(), roll.iter().map(|die| die.result).sum::<i32>());
+
+        let owned: Vec<RollResult> = roll.clone().into_iter().collect();
+        assert_eq!(roll.iter().collect::<Vec<_>>(), owned);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_bracketed_count_and_sides() {
+        for _ in 0..100 {
+            let roll = roll("(1d4)d6".to_string()).unwrap();
+            let (count_die, dice) = roll.rolls.split_first().unwrap();
+            assert_eq!(4, count_die.sides);
+            assert_eq!(count_die.result as usize, dice.len());
+            assert!(dice.iter().all(|r| r.sides == 6));
+            assert_eq!(dice.iter().map(|r| r.result).sum::<i32>(), roll.get_result());
+        }
+
+        let fixed = roll("(2 * 3)d(4 - 3)".to_string()).unwrap();
+        assert_eq!(6, fixed.rolls.iter().filter(|r| r.sides == 1).count());
+
+        assert_eq!(0, roll("(1 - 1)d6".to_string()).unwrap().get_result());
+        assert_eq!(0, roll("(1 - 3)d6".to_string()).unwrap().get_result());
+        assert_eq!(
+            Err(RollError::InvalidDiceSides { fragment: "2d(1-1)".to_string(), position: 0 }),
+            roll("2d(1 - 1)".to_string()).map(|_| ())
+        );
+        // A count or sides that can't be solved is an error, whichever face the dice show.
+        for input in &["(1/0)d6", "2d(6/(1d2-1))", "(2147483647+1)d6"] {
+            assert!(roll_with_forced(input.to_string(), &[1]).is_err(), "{}", input);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reject_dice_without_sides() {
+        assert_eq!(
+            Err(RollError::InvalidDiceSides { fragment: "d0".to_string(), position: 0 }),
+            roll("d0".to_string()).map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::InvalidDiceSides { fragment: "3d0".to_string(), position: 4 }),
+            roll("2 + 3d0".to_string()).map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::InvalidDiceSides { fragment: "2d(1-3)".to_string(), position: 0 }),
+            roll("2d(1 - 3)".to_string()).map(|_| ())
+        );
+        // Even when no dice would be rolled.
+        assert!(roll("0d0".to_string()).is_err());
+
+        // As many sides as an i32 can hold can be rolled, though not always added up.
+        for _ in 0..20 {
+            assert!(roll("1d2147483647".to_string()).unwrap().get_result() >= 1);
+        }
+        assert_eq!(
+            Err(RollError::Overflow),
+            roll_with_forced("2d2147483647".to_string(), &[2147483647, 1]).map(|_| ())
+        );
+        assert_eq!(1, roll("1d1 * 0 + 1".to_string()).unwrap().get_result());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_with_rounding_modes() {
+        let roll_rounded = |input: &str, rounding: RoundingMode| {
+            let options = RollOptions { rounding, ..RollOptions::default() };
+            roll_with_options(input.to_string(), &options).unwrap().get_result()
+        };
+
+        assert_eq!(4, roll("7 / 2".to_string()).unwrap().get_result());
+        assert_eq!(4, roll_rounded("7 / 2", RoundingMode::Nearest));
+        assert_eq!(3, roll_rounded("7 / 2", RoundingMode::Floor));
+        assert_eq!(4, roll_rounded("7 / 2", RoundingMode::Ceil));
+        assert_eq!(3, roll_rounded("7 / 2", RoundingMode::Truncate));
+        assert_eq!(-4, roll_rounded("(0 - 7) / 2", RoundingMode::Floor));
+        assert_eq!(-3, roll_rounded("(0 - 7) / 2", RoundingMode::Truncate));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn find_natural_rolls_and_criticals() {
+        let roll = roll("1d20 + 2d6 + 20".to_string()).unwrap();
+        let d20s = roll.get_natural_rolls(20);
+        assert_eq!(1, d20s.len());
+        assert_eq!(2, roll.get_natural_rolls(6).len());
+        assert!(roll.get_natural_rolls(8).is_empty());
+        assert!(roll.get_natural_rolls(0).is_empty());
+
+        assert!(roll.is_critical(20, 1));
+        assert_eq!(d20s[0] >= 18, roll.is_critical(20, 18));
+        assert_eq!(d20s[0] == 20, roll.is_critical(20, 20));
+        assert!(!roll.is_critical(8, 1));
+    }
+
+    fn roll_seeded(input: &str, seed: u64) -> DiceRolls {
+        roll_with_rng(input, &RollOptions::default(), &mut StdRng::seed_from_u64(seed)).unwrap()
+    }
+
+    #[test]
+    fn apply_modifiers_in_order() {
+        for seed in 0..200 {
+            // The same seed always gives the same dice.
+            let first = roll_seeded("4d6r1!kh3>=4", seed);
+            let second = roll_seeded("4d6r1!kh3>=4", seed);
+            assert_eq!(first.get_rolls_string(), second.get_rolls_string());
+            assert_eq!(first.get_result(), second.get_result());
+
+            // Rerolls come before explosions, so a rerolled 1 on a d2 always becomes
+            // a 2, which always explodes.
+            assert!(roll_seeded("1d2r1!", seed).get_result() >= 3);
+
+            // Explosions come before keeping, so any exploded dice can be kept.
+            let exploded = roll_seeded("4d6!kh3", seed);
+            let mut results: Vec<i32> = exploded.rolls.iter().map(|r| r.result).collect();
+            results.sort();
+            results.reverse();
+            assert_eq!(results[..3].iter().sum::<i32>(), exploded.get_result());
+            assert_eq!(3, exploded.rolls.iter().filter(|r| r.kept).count());
+
+            // Keeping comes before counting successes, so dropped dice never count.
+            let pool = roll_seeded("4d6kh2>=1", seed);
+            assert_eq!(Some(2), pool.get_successes());
+        }
+
+        for seed in 0..50 {
+            let dropped = roll_seeded("4d6dl1", seed);
+            let lowest = dropped.rolls.iter().map(|r| r.result).min().unwrap();
+            assert_eq!(dropped.rolls.iter().map(|r| r.result).sum::<i32>() - lowest, dropped.get_result());
+
+            let lowest_kept = roll_seeded("2d20kl", seed);
+            assert_eq!(lowest_kept.rolls.iter().map(|r| r.result).min().unwrap(), lowest_kept.get_result());
+
+            let highest_dropped = roll_seeded("3d6dh2", seed);
+            assert_eq!(highest_dropped.rolls.iter().map(|r| r.result).min().unwrap(), highest_dropped.get_result());
+        }
+    }
+
+    #[test]
+    fn build_from_parts() {
+        let to_strings = |v: &[&str]| v.iter().map(|e| e.to_string()).collect::<Vec<String>>();
+        let dice = vec![
+            RollResult { sides: 6, result: 4 },
+            RollResult { sides: 6, result: 5 },
+            RollResult { sides: 0, result: 3 },
+        ];
+
+        let built = DiceRolls::from_parts(dice.clone(), to_strings(&["9", "3", "*"]), to_strings(&["2d6", "3", "*"])).unwrap();
+        assert_eq!(27, built.get_result());
+        assert_eq!(12, built.get_sum_of_rolls());
+        assert_eq!("d6 -> [4], d6 -> [5], d0 -> [3]", built.get_rolls_string());
+        assert_eq!("[2d6 * 3]", built.get_rolls_formula_string_as_infix());
+
+        assert_eq!(
+            Err(RollError::MissingOperand("+".to_string())),
+            DiceRolls::from_parts(dice.clone(), to_strings(&["9", "+"]), to_strings(&["2d6", "+"])).map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::MismatchedFormulas),
+            DiceRolls::from_parts(dice.clone(), to_strings(&["9", "3", "+"]), to_strings(&["2d6", "3", "-"])).map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "2d6".to_string(), position: 0 }),
+            DiceRolls::from_parts(dice, to_strings(&["2d6"]), to_strings(&["2d6"])).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn format_as_json() {
+        let to_strings = |v: &[&str]| v.iter().map(|e| e.to_string()).collect::<Vec<String>>();
+        let built = DiceRolls::from_parts(
+            vec![
+                RollResult { sides: 6, result: 4 },
+                RollResult { sides: 6, result: 5 },
+                RollResult { sides: 0, result: 3 },
+            ],
+            to_strings(&["9", "3", "+"]),
+            to_strings(&["2d6", "3", "+"]),
+        ).unwrap();
+        assert_eq!(
+            "{\"result\":12,\"sum_of_rolls\":12,\"successes\":null,\"dice\":[{\"sides\":6,\"result\":4},\
+             {\"sides\":6,\"result\":5},{\"sides\":0,\"result\":3}],\"formula\":\"[9 + 3]\",\"rolls_formula\":\"[2d6 + 3]\"}",
+            built.to_json()
+        );
+
+        let pool = roll("3d6>=1".to_string()).unwrap();
+        assert!(pool.to_json().contains("\"successes\":3,"));
+        assert!(roll("1 / 0".to_string()).unwrap().to_json().starts_with("{\"result\":null,\"sum_of_rolls\":1,"));
+
+        assert_eq!("\"a\\\"b\\\\c\\n\\u0001\"", json_string("a\"b\\c\n\u{1}"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn multiply_with_x() {
+        for seed in 0..20 {
+            let expected = roll_seeded("2d6 * 3", seed).get_result();
+            assert_eq!(expected, roll_seeded("2d6 x 3", seed).get_result());
+            assert_eq!(expected, roll_seeded("2d6 X 3", seed).get_result());
+            assert_eq!(expected, roll_seeded("2d6x3", seed).get_result());
+        }
+        assert_eq!(24, roll("(2 + 2) x (3X2)".to_string()).unwrap().get_result());
+
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "xd6".to_string(), position: 0 }),
+            roll("xd6".to_string()).map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "x3".to_string(), position: 6 }),
+            roll("2d6 * x3".to_string()).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reroll_same_formula() {
+        let original = roll_seeded("4d6kh3 + 2", 7);
+        let before = original.get_rolls_string();
+        let mut differs = false;
+        for _ in 0..50 {
+            let rerolled = original.reroll().unwrap();
+            assert_eq!(original.get_rolls_formula_string_as_infix(), rerolled.get_rolls_formula_string_as_infix());
+            assert_eq!(5, rerolled.rolls.len());
+            differs |= rerolled.get_rolls_string() != before;
+        }
+        assert!(differs);
+        assert_eq!(before, original.get_rolls_string());
+
+        let options = RollOptions { rounding: RoundingMode::Floor, ..RollOptions::default() };
+        let floored = roll_with_options("7 / 2".to_string(), &options).unwrap();
+        assert_eq!(3, floored.reroll().unwrap().get_result());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_against_difficulty_class() {
+        for seed in 0..50 {
+            let check = roll_seeded("1d20 + 5 dc15", seed);
+            assert_eq!(Some(15), check.get_dc());
+            assert_eq!(Some(check.get_result() >= 15), check.succeeded());
+            assert_eq!(Some(15), check.reroll().unwrap().get_dc());
+        }
+
+        assert_eq!(Some(true), roll("15 vs15".to_string()).unwrap().succeeded());
+        assert_eq!(Some(false), roll("14 VS 15".to_string()).unwrap().succeeded());
+        assert_eq!(None, roll("1d20 + 5".to_string()).unwrap().succeeded());
+
+        // A result that can't be solved neither meets nor misses the difficulty class.
+        let unsolved = roll("1d6 / 0 dc5".to_string()).unwrap();
+        assert_eq!(None, unsolved.succeeded());
+        assert!(!unsolved.describe().contains("DC"));
+        assert_eq!(None, roll("1d20 + 5".to_string()).unwrap().get_dc());
+        assert_eq!(Err(RollError::EmptyExpression), roll("dc15".to_string()).map(|_| ()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn subtract_with_unicode_minus() {
+        for seed in 0..20 {
+            let expected = roll_seeded("2d6 - 1", seed).get_result();
+            assert_eq!(expected, roll_seeded("2d6−1", seed).get_result());
+            assert_eq!(expected, roll_seeded("2d6 − 1", seed).get_result());
+            assert_eq!(expected, roll_seeded("2d6-1", seed).get_result());
+        }
+        assert_eq!(6, roll("10−4".to_string()).unwrap().get_result());
+        assert_eq!(13, roll("10 − −3".to_string()).unwrap().get_result());
+        assert_eq!(-7, roll("−3 − 4".to_string()).unwrap().get_result());
+        assert_eq!(2, roll("(5−1)−(3-1)".to_string()).unwrap().get_result());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn multiply_and_divide_with_unicode_operators() {
+        for seed in 0..20 {
+            assert_eq!(roll_seeded("2d6 * 3", seed).get_result(), roll_seeded("2d6×3", seed).get_result());
+            assert_eq!(roll_seeded("d100 / 15", seed).get_result(), roll_seeded("d100÷15", seed).get_result());
+            assert_eq!(
+                roll_seeded("2d6 * 3 / 2", seed).get_result(),
+                roll_seeded("2d6 × 3÷2", seed).get_result()
+            );
+        }
+        assert_eq!(27, roll_with_forced("2d6×3".to_string(), &[4, 5]).unwrap().get_result());
+        assert_eq!(3, roll_with_forced("d100÷15".to_string(), &[45]).unwrap().get_result());
+        assert_eq!(4, roll("12÷3".to_string()).unwrap().get_result());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_average_dice() {
+        for seed in 0..20 {
+            let rolls = roll_seeded("4da", seed);
+            assert_eq!(4, rolls.iter().count());
+            for r in &rolls {
+                assert_eq!(6, r.sides);
+                assert!(r.result >= 2 && r.result <= 5);
+            }
+            assert_eq!(rolls.get_result(), roll_seeded("4d6a", seed).get_result());
+        }
+        assert_eq!(
+            Err(RollError::InvalidAverageDice { fragment: "3d8a".to_string(), position: 0 }),
+            roll("3d8a".to_string()).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn keep_across_a_set() {
+        for seed in 0..20 {
+            let rolls = roll_seeded("{4d6, 1d8}kh3 + 1", seed);
+            let dice: Vec<&DiceRoll> = rolls.rolls.iter().filter(|r| r.sides != 0).collect();
+            let mut results: Vec<i32> = dice.iter().map(|r| r.result).collect();
+            assert_eq!(5, results.len());
+            assert_eq!(8, dice[4].sides);
+            assert_eq!(3, dice.iter().filter(|r| r.kept).count());
+            results.sort();
+            assert_eq!(results[2..].iter().sum::<i32>() + 1, rolls.get_result());
+            assert_eq!(6, rolls.get_rolls_string().matches("->").count());
+        }
+        assert_eq!(10, roll("{3, 10, 1d4}kh1".to_string()).unwrap().get_result());
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "{4d6,1d8>=4}kh3".to_string(), position: 0 }),
+            roll("{4d6, 1d8>=4}kh3".to_string()).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn repeat_bracketed_formulas() {
+        for seed in 0..20 {
+            let rolls = roll_seeded("3(1d6 + 2) + 1", seed);
+            assert_eq!(3, rolls.rolls.iter().filter(|r| r.sides == 6).count());
+            assert_eq!(rolls.get_sum_of_rolls(), rolls.get_result());
+            assert_eq!(rolls.get_groups()[0].subtotal, rolls.get_result() - 1);
+
+            // Multiplying instead rolls the formula once.
+            assert_eq!(1, roll_seeded("3 * (1d6 + 2)", seed).rolls.iter().filter(|r| r.sides == 6).count());
+        }
+        assert_eq!(6, roll("2(1d1 * 0 + 3)".to_string()).unwrap().get_result());
+        assert_eq!(Some(4), roll("2(2d6>=1)".to_string()).unwrap().get_successes());
+
+        // A negative repeat takes the total away, and a repeat of zero rolls nothing.
+        assert_eq!(-6, roll("-2(3)".to_string()).unwrap().get_result());
+        assert_eq!(4, roll("10 + -2(3)".to_string()).unwrap().get_result());
+        let rolls = roll("0(1d6)".to_string()).unwrap();
+        assert_eq!(0, rolls.get_result());
+        assert!(rolls.rolls.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn combine_rolls() {
+        let weapon = roll_seeded("1d8 + 3", 1);
+        let sneak_attack = roll_seeded("2d6", 2);
+        let penalty = roll_seeded("1d4", 3);
+        let parts = [weapon.clone(), sneak_attack.clone(), penalty.clone()];
+
+        let damage = DiceRolls::combine(&parts, Operator::Subtract);
+        assert_eq!(
+            weapon.get_result() - sneak_attack.get_result() - penalty.get_result(),
+            damage.get_result()
+        );
+        assert_eq!(
+            vec!["1d8", "3", "+", "2d6", "-", "1d4", "-"],
+            damage.rolls_formula
+        );
+        let dice: Vec<i32> = parts.iter().flat_map(|part| part.rolls.iter().map(|r| r.result)).collect();
+        assert_eq!(dice, damage.rolls.iter().map(|r| r.result).collect::<Vec<i32>>());
+        assert_eq!(
+            format!("{}, {}, {}", weapon.get_rolls_string(), sneak_attack.get_rolls_string(), penalty.get_rolls_string()),
+            damage.get_rolls_string()
+        );
+        assert_eq!(4, damage.get_groups().len());
+
+        let checks = [roll_seeded("1d20 dc10", 1), roll_seeded("1d20 dc10", 2)];
+        assert_eq!(Some(10), DiceRolls::combine(&checks, Operator::Add).get_dc());
+        assert_eq!(None, DiceRolls::combine(&[checks[0].clone(), weapon], Operator::Add).get_dc());
+        assert_eq!(Err(RollError::EmptyExpression), DiceRolls::combine(&[], Operator::Add).get_result_checked());
+
+        // Halved and floored parts count for their own results.
+        let halved = roll("11 half".to_string()).unwrap();
+        let three = roll("3".to_string()).unwrap();
+        assert_eq!(5 + 3, DiceRolls::combine(&[halved.clone(), three.clone()], Operator::Add).get_result());
+        assert_eq!(3 + 5, DiceRolls::combine(&[three.clone(), halved.clone()], Operator::Add).get_result());
+        let both = DiceRolls::combine(&[halved.clone(), halved], Operator::Add);
+        assert_eq!(10, both.get_result());
+        assert_eq!(None, both.half);
+        let options = RollOptions { floor_result_at: Some(1), ..RollOptions::default() };
+        let floored = roll_with_options("1 - 5".to_string(), &options).unwrap();
+        assert_eq!(1 - 3, DiceRolls::combine(&[floored, three], Operator::Subtract).get_result());
+        let halved_dice = roll_with_forced("2d6 half".to_string(), &[4, 5]).unwrap();
+        let damage = DiceRolls::combine(&[halved_dice, roll("1".to_string()).unwrap()], Operator::Add);
+        assert_eq!(4 + 1, damage.get_result());
+        assert_eq!(vec![4, 5, 1], damage.rolls.iter().map(|r| r.result).collect::<Vec<i32>>());
+        assert_eq!("2d6 half", damage.get_groups()[0].notation);
+    }
+
+    #[test]
+    fn expose_rpn_tokens() {
+        for seed in 0..20 {
+            let rolls = roll_seeded("2d6 − 1 x 1d4", seed);
+            let tokens = rolls.get_rpn_tokens();
+            assert_eq!(rolls.formula.len(), tokens.len());
+            assert_eq!(RpnToken::Value(rolls.formula[0].parse().unwrap()), tokens[0]);
+            assert_eq!(RpnToken::Operator(Operator::Multiply), tokens[3]);
+            assert_eq!(RpnToken::Operator(Operator::Subtract), tokens[4]);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_several_formulas() {
+        let rolls = roll_multi("1d20 + 5; 1d6 + 2 dc3".to_string(), DEFAULT_MULTI_SEPARATOR).unwrap();
+        assert_eq!(2, rolls.len());
+        assert_eq!(vec!["1d20", "5", "+"], rolls[0].rolls_formula);
+        assert_eq!(vec!["1d6", "2", "+"], rolls[1].rolls_formula);
+        assert_eq!(Some(true), rolls[1].succeeded());
+
+        // Only a separator outside of brackets separates formulas.
+        let rolls = roll_multi("10 / (10 / 2)".to_string(), '/').unwrap();
+        assert_eq!(vec![10, 5], rolls.iter().map(|r| r.get_result()).collect::<Vec<i32>>());
+        assert_eq!(1, roll_multi("10 / 2".to_string(), ';').unwrap().len());
+
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "@".to_string(), position: 9 }),
+            roll_multi("1d6; 1 + @".to_string(), ';').map(|_| ())
+        );
+        assert_eq!(Err(RollError::EmptyExpression), roll_multi("1d6;".to_string(), ';').map(|_| ()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn limit_operations() {
+        assert_eq!(
+            Err(RollError::ComputationLimit),
+            roll("1000(1000(1000d6))".to_string()).map(|_| ())
+        );
+        assert_eq!(Err(RollError::ComputationLimit), roll("20000d6".to_string()).map(|_| ()));
+        assert_eq!(Err(RollError::ComputationLimit), roll("(100000(0))d6".to_string()).map(|_| ()));
+
+        let options = RollOptions { max_operations: 5, ..RollOptions::default() };
+        assert!(roll_with_options("2d6 + 3d6".to_string(), &options).is_ok());
+        assert_eq!(
+            Err(RollError::ComputationLimit),
+            roll_with_options("2d6 + (1d1 + 1)d6 + 2d6".to_string(), &options).map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::ComputationLimit),
+            roll_with_options("6(2)".to_string(), &options).map(|_| ())
+        );
+
+        // Each roll of a formula has its own limit.
+        let rolls = roll_with_options("5d6".to_string(), &options).unwrap();
+        assert!(rolls.reroll().is_ok());
+        assert!(roll_aggregate("1d6".to_string(), 10_000, AggregateMode::Max).is_ok());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn format_with_bracket_styles() {
+        let rolls = roll("(2 - 1) * 3 + 4".to_string()).unwrap();
+        assert_eq!("[[[2 - 1] * 3] + 4]", rolls.get_formula_string_as_infix());
+        assert_eq!(
+            rolls.get_formula_string_as_infix(),
+            rolls.get_formula_string_as_infix_with(BracketStyle::default())
+        );
+        assert_eq!("(((2 - 1) * 3) + 4)", rolls.get_formula_string_as_infix_with(BracketStyle::Round));
+        assert_eq!("{{{2 - 1} * 3} + 4}", rolls.get_formula_string_as_infix_with(BracketStyle::Curly));
+        assert_eq!("2 - 1 * 3 + 4", rolls.get_formula_string_as_infix_with(BracketStyle::None));
+
+        let rolls = roll("2d6 + 1".to_string()).unwrap();
+        assert_eq!("{2d6 + 1}", rolls.get_rolls_formula_string_as_infix_with(BracketStyle::Curly));
+        assert_eq!("2d6 + 1", rolls.get_rolls_formula_string_as_infix_with(BracketStyle::None));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn format_nested_values() {
+        // Notation given to from_parts may hold brackets of its own, spaced as it likes.
+        let rolls = DiceRolls::from_parts(
+            vec![RollResult { sides: 4, result: 2 }, RollResult { sides: 6, result: 5 }],
+            vec!["5".to_string(), "2".to_string(), "1".to_string(), "-".to_string(), "*".to_string()],
+            vec!["( 1d4 )d6".to_string(), "2".to_string(), "1".to_string(), "-".to_string(), "*".to_string()],
+        )
+        .unwrap();
+        assert_eq!("[5 * [2 - 1]]", rolls.get_formula_string_as_infix());
+        assert_eq!("[( 1d4 )d6 * [2 - 1]]", rolls.get_rolls_formula_string_as_infix());
+        assert_eq!("(( 1d4 )d6 * (2 - 1))", rolls.get_rolls_formula_string_as_infix_with(BracketStyle::Round));
+
+        let rolls = roll("((1 + 2) * (3 - (8 / 4))) + 2d(1)".to_string()).unwrap();
+        assert_eq!("[[[1 + 2] * [3 - [8 / 4]]] + 2d(1)]", rolls.get_rolls_formula_string_as_infix());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_savage_worlds_traits() {
+        for _ in 0..100 {
+            let rolls = roll_savage(8).unwrap();
+            let groups = rolls.get_groups();
+            assert_eq!(2, groups.len());
+            assert!(groups[0].rolls.iter().all(|r| r.sides == 8));
+            assert!(groups[1].rolls.iter().all(|r| r.sides == 6));
+            let (trait_total, wild_total) = (groups[0].subtotal, groups[1].subtotal);
+            assert_eq!(trait_total.max(wild_total), rolls.get_result());
+            assert_eq!(rolls.get_result(), rolls.get_sum_of_rolls());
+
+            // Only the dice of the higher total count.
+            let kept_sides = if trait_total >= wild_total { 8 } else { 6 };
+            assert!(rolls.get_kept_rolls().iter().all(|r| r.sides == kept_sides));
+            assert_eq!(rolls.rolls.len(), groups[0].rolls.len() + groups[1].rolls.len());
+        }
+
+        assert!(roll_savage(2).unwrap().get_result() >= 1);
+        assert_eq!(
+            Err(RollError::InvalidDiceSides { fragment: "1d1!".to_string(), position: 0 }),
+            roll_savage(1).map(|_| ())
+        );
+        assert!(roll_savage(0).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn describe_rolls() {
+        let rolls = roll_with_forced("2d6 + 3".to_string(), &[4, 5]).unwrap();
+        assert_eq!("Rolled 2d6 (4, 5) and added 3, for a total of 12.", rolls.describe());
+
+        let rolls = roll_with_forced("10 - 1d4 * 2".to_string(), &[3]).unwrap();
+        assert_eq!(
+            "Rolled 1d4 (3), then worked out [10 - [3 * 2]], for a total of 4.",
+            rolls.describe()
+        );
+        let rolls = roll_with_forced("(10 - 1d4) * 2 + 1".to_string(), &[3]).unwrap();
+        assert_eq!(
+            "Started with 10, subtracted 1d4 (3), multiplied by 2 and added 1, for a total of 15.",
+            rolls.describe()
+        );
+        let rolls = roll_with_forced("4d6kh3 + 1d20 dc30".to_string(), &[1, 6, 4, 5, 12]).unwrap();
+        assert_eq!(
+            "Rolled 4d6kh3 (1 not counted, 6, 4, 5) and added 1d20 (12), for a total of 27, which misses the DC of 30.",
+            rolls.describe()
+        );
+        let rolls = roll_with_forced("3d10>=7".to_string(), &[7, 2, 9]).unwrap();
+        assert_eq!("Rolled 3d10>=7 (7, 2, 9), for a total of 18 and 2 successes.", rolls.describe());
+        assert_eq!("Started with 5, for a total of 5.", roll("5".to_string()).unwrap().describe());
+        assert_eq!("Worked out [10 - [1 * 2]], for a total of 8.", roll("10 - 1 * 2".to_string()).unwrap().describe());
+
+        let parts = DiceRolls::from_parts(
+            vec![RollResult { sides: 6, result: 4 }],
+            vec!["4".to_string(), "2".to_string(), "/".to_string()],
+            vec!["1d6".to_string(), "2".to_string(), "/".to_string()],
+        )
+        .unwrap();
+        assert_eq!("Worked out [4 / 2], for a total of 2.", parts.describe());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_each_line() {
+        let results = roll_lines("1d20 + 5\r\n  \n  # Damage, if it hits\n  2d6 + @\n3 * 4\n");
+        assert_eq!(3, results.len());
+        assert_eq!(vec!["1d20", "5", "+"], results[0].as_ref().unwrap().rolls_formula);
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "@".to_string(), position: 8 }),
+            results[1].as_ref().map(|_| ()).map_err(|e| e.clone())
+        );
+        assert_eq!(12, results[2].as_ref().unwrap().get_result());
+
+        assert!(roll_lines("").is_empty());
+        assert!(roll_lines("\n# Nothing to roll\n").is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn show_verbose_strings() {
+        let rolls = roll_with_forced("2d6 + 3".to_string(), &[4, 5]).unwrap();
+        assert_eq!("2d6[4+5=9] + 3 = 12", rolls.get_verbose_string());
+
+        let rolls = roll_with_forced("(4d6kh3 − 1) × 1d20".to_string(), &[1, 6, 4, 5, 2]).unwrap();
+        assert_eq!("(4d6kh3[~1~+6+4+5=15] − 1) × 1d20[2] = 28", rolls.get_verbose_string());
+        let rolls = roll_with_forced("1d8 - (2 + 1d4 + (2d6 − 3))".to_string(), &[8, 4, 6, 6]).unwrap();
+        assert_eq!(
+            "1d8[8] - ((2 + 1d4[4]) + (2d6[6+6=12] − 3)) = -7",
+            rolls.get_verbose_string()
+        );
+        assert_eq!("7 = 7", roll("7".to_string()).unwrap().get_verbose_string());
+        assert_eq!("1d6[3] / 0", roll_with_forced("1d6 / 0".to_string(), &[3]).unwrap().get_verbose_string());
+
+        let parts = DiceRolls::from_parts(
+            vec![RollResult { sides: 6, result: 4 }],
+            vec!["4".to_string(), "2".to_string(), "/".to_string()],
+            vec!["1d6".to_string(), "2".to_string(), "/".to_string()],
+        )
+        .unwrap();
+        assert_eq!("4 / 2 = 2", parts.get_verbose_string());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn break_down_percentile_dice() {
+        let breakdown = RollOptions { percentile_breakdown: true, ..RollOptions::default() };
+        for seed in 0..200 {
+            let options = RollOptions { seed: Some(seed), ..breakdown.clone() };
+            let rolls = roll_with_options("d%".to_string(), &options).unwrap();
+            let result = rolls.get_result();
+            assert!((1..=100).contains(&result));
+            let expected = format!("d100 -> [{}0 + {} = {}]", result % 100 / 10, result % 10, result);
+            assert_eq!(expected, rolls.get_rolls_string());
+        }
+
+        let mut forced = ForcedRolls {
+            forced: [70, 3, 0, 10, 0, 7].iter(),
+            fallback: thread_rng(),
+        };
+        let rolls = roll_with_roller("3d%", &breakdown, &mut forced).unwrap();
+        assert_eq!(
+            "d100 -> [70 + 3 = 73], d100 -> [00 + 0 = 100], d100 -> [00 + 7 = 7]",
+            rolls.get_rolls_string()
+        );
+        assert_eq!(180, rolls.get_result());
+
+        // Without the option, and for d100s, percentile dice are a single number.
+        let rolls = roll_with_forced("d% + d100".to_string(), &[73, 100]).unwrap();
+        assert_eq!("d100 -> [73], d100 -> [100]", rolls.get_rolls_string());
+        let mut forced = ForcedRolls {
+            forced: [73].iter(),
+            fallback: thread_rng(),
+        };
+        let rolls = roll_with_roller("d100", &breakdown, &mut forced).unwrap();
+        assert_eq!("d100 -> [73]", rolls.get_rolls_string());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn natural_rolls_ignore_modifiers() {
+        let rolls = roll_with_forced("2d6pe1".to_string(), &[6, 4, 2]).unwrap();
+        assert_eq!(vec![6, 4, 2], rolls.get_natural_rolls(6));
+        assert_eq!(vec![7, 4, 3], rolls.get_kept_rolls().iter().map(|r| r.result).collect::<Vec<i32>>());
+        assert_eq!("d6 -> [7 (6)], d6 -> [4], d6 -> [3 (2)]", rolls.get_rolls_string());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn list_dice_types() {
+        let rolls = roll("1d20 + 2d6 + 1d20 - 5".to_string()).unwrap();
+        assert_eq!(vec!["1d20", "2d6"], rolls.get_dice_types());
+        assert!(roll("3 * 4".to_string()).unwrap().get_dice_types().is_empty());
+        assert_eq!(vec!["4d6kh3"], roll("(4d6kh3 + -2)".to_string()).unwrap().get_dice_types());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn floor_result_at_minimum() {
+        let options = RollOptions { floor_result_at: Some(1), ..RollOptions::default() };
+        let mut forced = ForcedRolls { forced: [1, 4].iter(), fallback: thread_rng() };
+        let rolls = roll_with_roller("1d4 - 3", &options, &mut forced).unwrap();
+        assert_eq!(1, rolls.get_result());
+        assert_eq!(vec![1], rolls.get_natural_rolls(4));
+        assert_eq!(3, roll_with_roller("1d4 - 1", &options, &mut forced).unwrap().get_result());
+        assert_eq!(-4, roll_with_forced("1d4 - 5".to_string(), &[1]).unwrap().get_result());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reject_doubled_operators() {
+        for seed in 0..20 {
+            assert_eq!(roll_seeded("2d6", seed).get_result(), roll_seeded("+2d6", seed).get_result());
+            assert_eq!(roll_seeded("2d6 * (1d4)", seed).get_result(), roll_seeded("+2d6 * (+1d4)", seed).get_result());
+        }
+        assert_eq!(
+            Err(RollError::UnexpectedOperator { operator: "+".to_string(), position: 6 }),
+            roll(" 2d6 ++ 3".to_string()).map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::UnexpectedOperator { operator: "*".to_string(), position: 5 }),
+            roll("2d6 +* 3".to_string()).map(|_| ())
+        );
+        assert_eq!(-1, roll("2 * -3 + 5".to_string()).unwrap().get_result());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn count_max_rolls() {
+        let rolls = roll_with_forced("3d20 + 2d6e1 + 4".to_string(), &[20, 3, 20, 6, 5]).unwrap();
+        assert_eq!(vec![(6, 1), (20, 2)], rolls.get_max_rolls());
+        let rolls = roll_with_forced("2d20kl1 + d00 + 1d1".to_string(), &[20, 4, 90, 1]).unwrap();
+        assert_eq!(vec![(1, 1), (10, 1)], rolls.get_max_rolls());
+        assert!(roll_with_forced("1d1 + 3".to_string(), &[0]).unwrap().get_max_rolls().is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn halve_results() {
+        assert_eq!(6, roll_with_forced("2d6 + 4 half".to_string(), &[4, 5]).unwrap().get_result());
+        assert_eq!(7, roll_with_forced("2d6 + 4 halfup".to_string(), &[4, 5]).unwrap().get_result());
+        assert_eq!(-2, roll_with_forced("1d4 - 6 halfup".to_string(), &[1]).unwrap().get_result());
+        let check = roll_with_forced("2d6 + 4 half dc7".to_string(), &[4, 5]).unwrap();
+        assert_eq!((Some(7), Some(false)), (check.get_dc(), check.succeeded()));
+        assert_eq!(13, check.get_sum_of_rolls());
+        assert!((3..=8).contains(&check.reroll().unwrap().get_result()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_zero_indexed_dice() {
+        let zero_indexed = RollOptions { zero_indexed_dice: true, ..RollOptions::default() };
+        let mut seen = BTreeMap::new();
+        for seed in 0..200 {
+            let options = RollOptions { seed: Some(seed), ..zero_indexed.clone() };
+            let rolls = roll_with_options("1d10 + d% + 1d1 + 1da".to_string(), &options).unwrap();
+            let dice = rolls.get_kept_rolls();
+            assert!(dice[0].result >= 0 && dice[0].result <= 9);
+            assert!(dice[1].result >= 0 && dice[1].result <= 99);
+            assert!(dice[2].result == 0 || dice[2].result == 1);
+            assert!(dice[3].result >= 2);
+            *seen.entry(dice[0].result).or_insert(0) += 1;
+        }
+        assert_eq!(10, seen.len());
+
+        let mut forced = ForcedRolls { forced: [5, 2, 5].iter(), fallback: thread_rng() };
+        let rolls = roll_with_roller("1d6! + 1d6>=5dbl", &zero_indexed, &mut forced).unwrap();
+        assert_eq!(vec![5, 2, 5], rolls.get_natural_rolls(6));
+        assert_eq!(vec![(6, 2)], rolls.get_max_rolls());
+        assert_eq!(Some(2), rolls.get_successes());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn compare_results() {
+        let low = roll_with_forced("1d20 + 2".to_string(), &[3]).unwrap();
+        let high = roll_with_forced("1d4 * 4".to_string(), &[4]).unwrap();
+        assert_eq!(Ordering::Less, low.compare(&high));
+        assert_eq!(Ordering::Greater, high.compare(&low));
+        assert!(low < high);
+        assert!(high == roll("16".to_string()).unwrap());
+        assert!(roll("1 / 0".to_string()).unwrap() < low);
+
+        let mut initiative = [low.clone(), high.clone(), roll("9".to_string()).unwrap()];
+        initiative.sort_by(|a, b| b.compare(a));
+        assert_eq!(vec![16, 9, 5], initiative.iter().map(|r| r.get_result()).collect::<Vec<i32>>());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reroll_once_and_keep() {
+        let kept = |input: &str, forced: &[i32]| {
+            let rolls = roll_with_forced(input.to_string(), forced).unwrap();
+            (rolls.get_kept_rolls().iter().map(|r| r.result).collect::<Vec<i32>>(), rolls.get_rolls_string())
+        };
+        assert_eq!((vec![3], "d20 -> [~1~], d20 -> [3]".to_string()), kept("d20rh1", &[1, 3]));
+        assert_eq!((vec![1], "d20 -> [~1~], d20 -> [1]".to_string()), kept("d20rh1", &[1, 1]));
+        assert_eq!((vec![2], "d20 -> [~2~], d20 -> [2]".to_string()), kept("d20rh<3", &[2, 2]));
+        assert_eq!(vec![1], kept("d20rh<3", &[2, 1]).0);
+        assert_eq!((vec![2], "d20 -> [~1~], d20 -> [2]".to_string()), kept("d20rk<3", &[2, 1]));
+        assert_eq!(vec![15], kept("d20rk<3", &[2, 15]).0);
+        assert_eq!(vec![12], kept("d20rk1", &[12]).0);
+        for seed in 0..20 {
+            let rolls = roll_seeded("4d6rk<3", seed);
+            assert_eq!(4, rolls.get_kept_rolls().len());
+            assert!(rolls.iter().count() <= 8);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn replay_rolls() {
+        let rolls = roll_with_forced("2d6 + 3".to_string(), &[4, 5]).unwrap();
+        assert_eq!("2d6 + 3 = [4, 5]", rolls.to_replay_string());
+        let rolls = roll_with_forced("(4d6kh3 - 1) * 2 dc20".to_string(), &[1, 6, 4, 5]).unwrap();
+        assert_eq!("(4d6kh3 - 1) * 2 dc20 = [1, 6, 4, 5]", rolls.to_replay_string());
+        assert_eq!("7 half = []", roll("7 half".to_string()).unwrap().to_replay_string());
+
+        for seed in 0..20 {
+            for input in &["4d6kh3 + 1d20 - 2", "3d6r1! * 2 halfup dc9", "{2d6, (1d4)d8}kh2", "2(1d6 + 1d4) + d%"] {
+                let rolls = roll_seeded(input, seed);
+                let replayed = DiceRolls::from_replay_string(&rolls.to_replay_string()).unwrap();
+                assert_eq!(rolls.get_result(), replayed.get_result());
+                assert_eq!(rolls.get_rolls_string(), replayed.get_rolls_string());
+                assert_eq!(rolls.succeeded(), replayed.succeeded());
+                assert_eq!(rolls.to_replay_string(), replayed.to_replay_string());
+            }
+        }
+
+        assert_eq!(12, DiceRolls::from_replay_string("2d6 + 3 = [4,5]").unwrap().get_result());
+        assert_eq!(Err(RollError::MismatchedFormulas), DiceRolls::from_replay_string("2d6 = [4]").map(|_| ()));
+        assert_eq!(Err(RollError::MismatchedFormulas), DiceRolls::from_replay_string("2d6 = [4, 5, 6]").map(|_| ()));
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "five".to_string(), position: 10 }),
+            DiceRolls::from_replay_string("2d6 = [4, five]").map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "2d6 + 3".to_string(), position: 0 }),
+            DiceRolls::from_replay_string("2d6 + 3").map(|_| ())
+        );
+
+        let impossible = |face: i32, position: usize| Err(RollError::ImpossibleFace { face, position });
+        assert_eq!(impossible(100, 11), DiceRolls::from_replay_string("2d6 + 3 = [100, 2]").map(|_| ()));
+        assert_eq!(impossible(-7, 11), DiceRolls::from_replay_string("2d6 + 3 = [-7, 2]").map(|_| ()));
+        assert_eq!(impossible(0, 14), DiceRolls::from_replay_string("2d6 + 3 = [2, 0]").map(|_| ()));
+        assert_eq!(impossible(1, 8), DiceRolls::from_replay_string("1d6a = [1]").map(|_| ()));
+        assert_eq!(impossible(101, 7), DiceRolls::from_replay_string("1d% = [101]").map(|_| ()));
+        assert_eq!(impossible(17, 8), DiceRolls::from_replay_string("1d66 = [17]").map(|_| ()));
+        assert_eq!(Ok(1), DiceRolls::from_replay_string("1d1 = [1]").map(|rolls| rolls.get_result()));
+        assert_eq!(Ok(100), DiceRolls::from_replay_string("1d% = [100]").map(|rolls| rolls.get_result()));
+    }
+
+    #[test]
+    fn validate_without_rolling() {
+        for input in &["2d6 + 3", "(4d6kh3 + 2) * 2 dc12", "3(1d6 + 2)", "{4d6, 1d8}kh3", "2d(1d4 + 1)", "d%"] {
+            assert_eq!(Ok(()), validate(input));
+            assert!(is_valid(input));
+        }
+        assert_eq!(Err(RollError::EmptyExpression), validate(" "));
+        assert_eq!(Err(RollError::UnbalancedParentheses { position: 0, parenthesis: '(' }), validate("(2d6 + 3"));
+        assert_eq!(Err(RollError::MissingOperand("*".to_string())), validate("2d6 *"));
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "@".to_string(), position: 6 }),
+            validate("2d6 + @")
+        );
+        assert_eq!(
+            Err(RollError::InvalidDiceSides { fragment: "3d0".to_string(), position: 4 }),
+            validate("1 + 3d0")
+        );
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "@".to_string(), position: 4 }),
+            validate("1 + 2d(1d4 + @)")
+        );
+        assert_eq!(
+            Err(RollError::InvalidAverageDice { fragment: "3d8a".to_string(), position: 0 }),
+            validate("3(3d8a)")
+        );
+        assert!(!is_valid("2d6 ++ 3"));
+        assert!(is_valid("1d6 / (1 - 1)"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_negative_dice() {
+        let rolls = roll_with_forced("2d6 + -2d4".to_string(), &[4, 5, 3, 1]).unwrap();
+        assert_eq!(5, rolls.get_result());
+        assert_eq!(5, rolls.get_sum_of_rolls());
+        assert_eq!("d6 -> [4], d6 -> [5], d4 -> [-3], d4 -> [-1]", rolls.get_rolls_string());
+        assert_eq!(vec![3, 1], rolls.get_natural_rolls(4));
+
+        let rolls = roll_with_forced("-1d20 + 25".to_string(), &[18]).unwrap();
+        assert_eq!(7, rolls.get_result());
+        let rolls = roll_with_forced("10 - -3d6e1kh1".to_string(), &[2, 6, 3]).unwrap();
+        assert_eq!(17, rolls.get_result());
+        assert_eq!("d0 -> [10], d6 -> [~-3 (-2)~], d6 -> [-7 (-6)], d6 -> [~-4 (-3)~]", rolls.get_rolls_string());
+        assert_eq!(-3, roll_with_forced("{1d6, -1d8}kh1 - 4".to_string(), &[1, 5]).unwrap().get_result());
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "-6d10>=7".to_string(), position: 4 }),
+            roll("1 + -6d10>=7".to_string()).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_default_sides() {
+        let six = RollOptions { default_sides: Some(6), ..RollOptions::default() };
+        for seed in 0..20 {
+            let options = RollOptions { seed: Some(seed), ..six.clone() };
+            let single = roll_with_options("d".to_string(), &options).unwrap();
+            assert_eq!(1, single.rolls.len());
+            assert_eq!(6, single.rolls[0].sides);
+            let rolls = roll_with_options("3d + 1".to_string(), &options).unwrap();
+            let seeded = RollOptions { seed: Some(seed), ..RollOptions::default() };
+            assert_eq!(rolls.get_result(), roll_with_options("3d6 + 1".to_string(), &seeded).unwrap().get_result());
+            assert_eq!(3, rolls.rolls.iter().filter(|r| r.sides == 6).count());
+        }
+
+        let invalid = RollError::InvalidFragment { fragment: "3d".to_string(), position: 4 };
+        assert_eq!(Err(invalid.clone()), roll("1 + 3d".to_string()).map(|_| ()));
+        assert_eq!(Err(invalid), validate("1 + 3d"));
+        assert!(!is_valid("d"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn result_probability() {
+        let mut forced = ForcedRolls { forced: [6, 6, 3, 4].iter(), fallback: thread_rng() };
+        let options = RollOptions::default();
+        let twelve = roll_with_roller("2d6", &options, &mut forced).unwrap();
+        assert!((twelve.get_result_probability().unwrap() - 1.0 / 36.0).abs() < 1e-9);
+        let seven = roll_with_roller("2d6 + 3 - 3", &options, &mut forced).unwrap();
+        assert!((seven.get_result_probability().unwrap() - 6.0 / 36.0).abs() < 1e-9);
+
+        assert_eq!(Some(1.0), roll("5 - 2".to_string()).unwrap().get_result_probability());
+        let negative = roll_with_forced("1d4 + -1d4".to_string(), &[4, 4]).unwrap();
+        assert!((negative.get_result_probability().unwrap() - 4.0 / 16.0).abs() < 1e-9);
+        let floored = RollOptions { floor_result_at: Some(3), ..RollOptions::default() };
+        let mut low = ForcedRolls { forced: [1].iter(), fallback: thread_rng() };
+        let raised = roll_with_roller("1d6", &floored, &mut low).unwrap();
+        assert_eq!(3, raised.get_result());
+        assert!((raised.get_result_probability().unwrap() - 0.5).abs() < 1e-9);
+
+        for input in &["2d6 * 2", "4d6kh3", "3d6!", "6d10>=7", "(1d4)d6", "1000d1000"] {
+            assert_eq!(None, roll(input.to_string()).unwrap().get_result_probability(), "{}", input);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn explode_on_threshold() {
+        let rolls = roll_with_forced("1d6!>=5 + 1d6!>=5".to_string(), &[5, 6, 2, 4]).unwrap();
+        assert_eq!(vec![5, 6, 2, 4], rolls.get_natural_rolls(6));
+        assert_eq!(17, rolls.get_result());
+
+        let pool = roll_with_forced("3d10!>=10>=7".to_string(), &[10, 3, 7, 9]).unwrap();
+        assert_eq!(4, pool.rolls.len());
+        assert_eq!(Some(3), pool.get_successes());
+
+        let (mut threshold, mut max_only) = (0, 0);
+        for seed in 0..50 {
+            threshold += roll_seeded("10d6!>=5", seed).rolls.len() - 10;
+            max_only += roll_seeded("10d6!", seed).rolls.len() - 10;
+        }
+        assert!(threshold > max_only);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn format_as_markdown() {
+        let rolls = roll_with_forced("2d6 + 3".to_string(), &[4, 5]).unwrap();
+        assert_eq!("**12** ⟵ 2d6 (`4`, `5`) + 3", rolls.to_markdown());
+        let kept = roll_with_forced("4d6kh3 * 2".to_string(), &[1, 6, 4, 5]).unwrap();
+        assert_eq!("**30** ⟵ 4d6kh3 (~~`1`~~, `6`, `4`, `5`) \\* 2", kept.to_markdown());
+        let pool = roll_with_forced("2d10!>=10>=7".to_string(), &[10, 2, 8]).unwrap();
+        assert_eq!("**20** ⟵ 2d10!\\>=10\\>=7 (`10`, `2`, `8`), for **2** successes", pool.to_markdown());
+        assert_eq!("**7** ⟵ 7", roll("7".to_string()).unwrap().to_markdown());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn tier_successes() {
+        let tier = |input: &str, forced: &[i32]| roll_with_forced(input.to_string(), forced).unwrap().get_success_tier();
+        assert_eq!(Some(SuccessTier::CritSuccess), tier("1d20 + 5 dc15 crit25", &[20]));
+        assert_eq!(Some(SuccessTier::Success), tier("1d20 + 5 dc15 crit25", &[10]));
+        assert_eq!(Some(SuccessTier::Fail), tier("1d20 + 5 dc15 crit25", &[9]));
+        assert_eq!(Some(SuccessTier::CritFail), tier("1d20 + 5 dc15 fumble6", &[1]));
+        assert_eq!(Some(SuccessTier::Fail), tier("1d20 + 5 dc15 fumble6", &[2]));
+        assert_eq!(Some(SuccessTier::CritSuccess), tier("1d20 crit20", &[20]));
+        assert_eq!(None, tier("1d20 crit20", &[19]));
+        assert_eq!(None, tier("1d20", &[20]));
+        assert!(SuccessTier::CritFail < SuccessTier::Fail && SuccessTier::Success < SuccessTier::CritSuccess);
+
+        let rolls = roll_with_forced("1d20 + 5 dc15 crit25 fumble5".to_string(), &[17]).unwrap();
+        assert_eq!("1d20 + 5 dc15 crit25 fumble5 = [17]", rolls.to_replay_string());
+        let replayed = DiceRolls::from_replay_string(&rolls.to_replay_string()).unwrap();
+        assert_eq!(Some(SuccessTier::Success), replayed.get_success_tier());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn accumulate_rolls_with_options() {
+        let sided = RollOptions { default_sides: Some(8), ..RollOptions::default() };
+        let mut accumulator = RollAccumulator::with_options(sided);
+        let first = accumulator.add("d").unwrap().get_result();
+        assert!((1..=8).contains(&first));
+        assert!(RollAccumulator::new().add("d").is_err());
+
+        let seeded = RollOptions { seed: Some(3), ..RollOptions::default() };
+        let mut accumulator = RollAccumulator::with_options(seeded);
+        let first = accumulator.add("4d6").unwrap().get_rolls_string();
+        assert_eq!(first, accumulator.add("4d6").unwrap().get_rolls_string());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn accumulate_rolls() {
+        let mut accumulator = RollAccumulator::new();
+        assert_eq!(0, accumulator.total());
+        assert_eq!(5, accumulator.add("5").unwrap().get_result());
+        let second = accumulator.add("2d6 - 1").unwrap().get_result();
+        assert_eq!(5 + second, accumulator.total());
+
+        assert!(accumulator.add("2d6 +").is_err());
+        assert_eq!(Err(RollError::DivisionByZero), accumulator.add("1d6 / 0").map(|_| ()));
+        assert_eq!(5 + second, accumulator.total());
+        let results: Vec<i32> = accumulator.history().iter().map(|roll| roll.get_result()).collect();
+        assert_eq!(vec![5, second], results);
+
+        let mut full = RollAccumulator::new();
+        full.add("2147483647").unwrap();
+        assert_eq!(Err(RollError::Overflow), full.add("1").map(|_| ()));
+        assert_eq!(2147483647, full.total());
+        assert_eq!(1, full.history().len());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_conditionals() {
+        let taken = roll_with_forced("1d20>=15 ? 2d6 : 0".to_string(), &[17, 4, 5]).unwrap();
+        assert_eq!(9, taken.get_result());
+        assert_eq!(vec![17, 4, 5], taken.faces);
+        assert_eq!(vec!["1d20>=15?2d6:0"], taken.get_dice_types());
+
+        let missed = roll_with_forced("1d20>=15 ? 2d6 : 0".to_string(), &[3, 6, 6]).unwrap();
+        assert_eq!(0, missed.get_result());
+        assert_eq!(vec![3], missed.faces);
+
+        let bracketed = roll_with_forced("(1d20>=15 ? 2d6 : 1d4) + 3 dc10".to_string(), &[2, 4]).unwrap();
+        assert_eq!(7, bracketed.get_result());
+        assert_eq!(Some(false), bracketed.succeeded());
+        assert_eq!(20, roll("0 ? 10 : 1 ? 20 : 30".to_string()).unwrap().get_result());
+
+        // The condition is read before it is halved or raised to `floor_result_at`.
+        let floored = RollOptions { floor_result_at: Some(1), ..RollOptions::default() };
+        assert_eq!(7, roll_with_options("(1 - 1 ? 5 : 7)".to_string(), &floored).unwrap().get_result());
+        assert_eq!(1, roll_with_options("(1 - 1 ? 5 : -7)".to_string(), &floored).unwrap().get_result());
+        assert_eq!(5, roll("(1 half ? 5 : 7)".to_string()).unwrap().get_result());
+
+        for seed in 0..20 {
+            let rolls = roll_seeded("1d20>=15 ? 2d6 : 0", seed);
+            let d20 = rolls.rolls[0].result;
+            assert_eq!(if d20 >= 15 { 3 } else { 2 }, rolls.rolls.len());
+            assert_eq!(rolls.rolls[1..].iter().map(|r| r.result).sum::<i32>(), rolls.get_result());
+        }
+
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "1d20?2d6".to_string(), position: 0 }),
+            roll("1d20 ? 2d6".to_string()).map(|_| ())
+        );
+        assert!(!is_valid("1d20 ? 2d6 : 1d"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn deviation_from_mean() {
+        let deviation = |input: &str, forced: &[i32]| roll_with_forced(input.to_string(), forced).unwrap().get_deviation();
+        // 2d6 has a mean of 7 and a variance of 35 / 6.
+        let spread = (35.0f64 / 6.0).sqrt();
+        assert!((deviation("2d6", &[6, 6]).unwrap() - 5.0 / spread).abs() < 1e-9);
+        assert!((deviation("2d6 + 3", &[1, 1]).unwrap() + 5.0 / spread).abs() < 1e-9);
+        assert!((deviation("2d6 - 1d4", &[3, 4, 2]).unwrap() - (5.0 - 4.5) / (35.0f64 / 6.0 + 1.25).sqrt()).abs() < 1e-9);
+        assert_eq!(Some(0.0), deviation("1d3", &[2]));
+        assert!((deviation("d20 half", &[20]).unwrap() - 9.5 / 33.25f64.sqrt()).abs() < 1e-9);
+
+        assert_eq!(None, deviation("5 + 3", &[]));
+        assert_eq!(None, deviation("2d6 * 2", &[3, 3]));
+        assert_eq!(None, deviation("4d6kh3", &[1, 2, 3, 4]));
+        assert!((sqrt(2.0) - 2.0f64.sqrt()).abs() < 1e-12);
+        assert!((sqrt(0.25) - 0.5).abs() < 1e-12);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_split_tokens() {
+        let tokens = vec!["(", "1d4", "+", "1", ")", "*", "2"].into_iter().map(String::from);
+        let rolls = roll_tokens(tokens).unwrap();
+        assert_eq!(vec!["1d4", "1", "+", "2", "*"], rolls.rolls_formula);
+        assert_eq!((rolls.rolls[0].result + 1) * 2, rolls.get_result());
+        assert_eq!(
+            Err(RollError::InvalidDiceSides { fragment: "1d0".to_string(), position: 2 }),
+            roll_tokens(vec!["5".to_string(), "+".to_string(), "1d0".to_string()]).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_best_and_worst() {
+        for _ in 0..20 {
+            let best = roll_best_of("1d20 + 2".to_string(), 5).unwrap();
+            let worst = roll_worst_of("1d20 + 2".to_string(), 5).unwrap();
+            assert_eq!(best.rolls[0].result + 2, best.get_result());
+            assert_eq!(worst.rolls[0].result + 2, worst.get_result());
+            assert_eq!(vec!["1d20", "2", "+"], best.rolls_formula);
+        }
+        let mut best_total = 0;
+        let mut worst_total = 0;
+        for _ in 0..50 {
+            best_total += roll_best_of("1d20".to_string(), 4).unwrap().get_result();
+            worst_total += roll_worst_of("1d20".to_string(), 4).unwrap().get_result();
+        }
+        assert!(best_total > worst_total);
+        assert_eq!(7, roll_best_of("7".to_string(), 1).unwrap().get_result());
+        assert_eq!(Err(RollError::EmptyExpression), roll_best_of("1d6".to_string(), 0).map(|_| ()));
+        assert_eq!(Err(RollError::DivisionByZero), roll_worst_of("1d6 / 0".to_string(), 2).map(|_| ()));
+    }
+
+    #[test]
+    fn count_dice_before_rolling() {
+        assert_eq!(Ok(0), count_dice("5 + 3"));
+        assert_eq!(Ok(400), count_dice("100d6 + 300d4"));
+        assert_eq!(Ok(3), count_dice("d% + 1d1 + d66"));
+        assert_eq!(Ok(4), count_dice("2da! * 2d6r1"));
+        assert_eq!(Ok(6), count_dice("{4d6, 1d8}kh3 + -1d4"));
+        assert_eq!(Ok(6), count_dice("3(1d6 + 1d4)"));
+        assert_eq!(Ok(3), count_dice("1d20>=15 ? 2d6 : 1d4"));
+        assert_eq!(Ok(6), count_dice("(1d4 + 1)d6"));
+        assert_eq!(Ok(3), count_dice("2d(1d4)"));
+        assert_eq!(Ok(1), count_dice("(1d4!)d6"));
+        assert_eq!(Ok(0), count_dice("0d6 + -0(1d6)"));
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "2q6".to_string(), position: 6 }),
+            count_dice("1d4 + 2q6")
+        );
+        for seed in 0..10 {
+            let rolls = roll_seeded("3d6 + (1d2)d4", seed);
+            assert!(rolls.rolls.len() as u64 <= count_dice("3d6 + (1d2)d4").unwrap());
+        }
+    }
+
+    #[test]
+    fn roll_averages() {
+        let average = |input: &str| roll_average(input.to_string()).unwrap();
+        assert_eq!(7, average("2d6").get_result());
+        assert_eq!(vec![3, 4, 3], average("3d6").get_natural_rolls(6));
+        assert_eq!(10, average("3d6").get_result());
+        assert_eq!(7, average("1d6 + 1d6").get_result());
+        assert_eq!(21, average("2d20").get_result());
+        assert_eq!(15, average("3d10 - 1").get_result());
+        assert_eq!(50, average("d% + d00").get_result() - 45);
+        assert_eq!(vec![38, 39], average("2d66").get_natural_rolls(66));
+        assert_eq!(vec![3, 4], average("2da").get_natural_rolls(6));
+        assert_eq!(8, average("4d6kh2").get_result());
+
+        let rolls = average("2d6 + 3");
+        assert_eq!("2d6[3+4=7] + 3 = 10", rolls.get_verbose_string());
+        assert_eq!("2d6 + 3 = [3, 4]", rolls.to_replay_string());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn tag_results() {
+        let rolls = roll_with_forced("2d6[fire] + 1d6[cold] + 3 - 1d4[fire]".to_string(), &[4, 5, 2, 3]).unwrap();
+        assert_eq!(11, rolls.get_result());
+        assert_eq!(6, rolls.get_result_by_tag("fire"));
+        assert_eq!(2, rolls.get_result_by_tag("cold"));
+        assert_eq!(0, rolls.get_result_by_tag("acid"));
+        let results = rolls.get_results_by_tag();
+        assert_eq!(Some(&3), results.get(&DiceTag::Untagged));
+        assert_eq!(3, results.len());
+        assert_eq!(Some("fire".to_string()), rolls.get_groups()[0].tag);
+        assert_eq!("2d6[fire]", rolls.get_groups()[0].notation);
+
+        // A term subtracted from a subtraction is added back.
+        let rolls = roll_with_forced("10 - (1d6[fire] - 1d4[fire])".to_string(), &[5, 2]).unwrap();
+        assert_eq!(-3, rolls.get_result_by_tag("fire"));
+        assert_eq!(Some(&10), rolls.get_results_by_tag().get(&DiceTag::Untagged));
+
+        // Tags are kept by replay strings.
+        let replayed = DiceRolls::from_replay_string(&rolls.to_replay_string()).unwrap();
+        assert_eq!(rolls.get_results_by_tag(), replayed.get_results_by_tag());
+        let parts = DiceRolls::from_parts(vec![], vec!["3".to_string()], vec!["3".to_string()]).unwrap();
+        assert!(parts.get_results_by_tag().is_empty());
+        assert!(roll("2d6[4]".to_string()).is_err());
+    }
+
+    #[test]
+    fn canonicalize_formulas() {
+        let cases = [
+            ("d6", "1d6"),
+            ("2 D 6", "2d6"),
+            ("{4D6, D8}kh3 + (1D4)D6", "{4d6, 1d8}kh3 + (1d4)d6"),
+            ("d06 + 007", "1d6 + 7"),
+            ("1,000 + d%", "1000 + 1d%"),
+            ("d00 + d10", "1d00 + 1d10"),
+            ("00d6 + 000", "0d6 + 0"),
+            ("-d4", "-1d4"),
+            ("2d6×3÷2 − 1", "((2d6 * 3) / 2) - 1"),
+            ("1 + 2 * 3", "1 + (2 * 3)"),
+            ("4d6kh03 + 6d10!>=10>=7", "4d6kh3 + 6d10!>=10>=7"),
+            ("{4d6,d8,03}kh3", "{4d6, 1d8, 3}kh3"),
+            ("(d4)d(d6 + 02)", "(1d4)d(1d6 + 2)"),
+            ("-2(d6+1)", "-2(1d6 + 1)"),
+            ("2d6[fire] + d6 [cold]", "2d6[fire] + 1d6[cold]"),
+            ("1d20>=15?2d6:0", "1d20>=15 ? 2d6 : 0"),
+            ("(d20>=15 ? 2d6 : 0) + 3", "(1d20>=15 ? 2d6 : 0) + 3"),
+            ("d2?d4?1:2:3", "1d2 ? (1d4 ? 1 : 2) : 3"),
+            ("(d2?1:0)?2:3", "(1d2 ? 1 : 0) ? 2 : 3"),
+            ("1d20+5 crit25 VS 15 half", "1d20 + 5 half dc15 crit25"),
+        ];
+        for &(input, canonical) in &cases {
+            assert_eq!(Ok(canonical.to_string()), canonicalize(input), "{}", input);
+            assert_eq!(Ok(canonical.to_string()), canonicalize(canonical), "{}", canonical);
+            // The canonical form rolls the same dice, for the same result.
+            let (rolled, canonical_rolled) = (roll_seeded(input, 7), roll_seeded(canonical, 7));
+            assert_eq!(rolled.get_result(), canonical_rolled.get_result());
+        }
+        assert_eq!(
+            Err(RollError::InvalidFragment { fragment: "2dq".to_string(), position: 6 }),
+            canonicalize("1d6 + 2dq")
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_uppercase_dice() {
+        let rolls = roll_with_forced("D20".to_string(), &[17]).unwrap();
+        assert_eq!(17, rolls.get_result());
+        assert_eq!("d20 -> [17]", rolls.get_rolls_string());
+
+        let rolls = roll_with_forced("2D6 + d4".to_string(), &[4, 5, 3]).unwrap();
+        assert_eq!(12, rolls.get_result());
+        assert_eq!(vec![6, 6, 4], rolls.iter().map(|r| r.sides).collect::<Vec<i32>>());
+        assert_eq!(roll_with_forced("(1D4)D6kh1".to_string(), &[2, 3, 6]).unwrap().get_result(), 6);
+
+        // A `D` that isn't the dice of a term is still an error.
+        for input in &["D", "2d6 + D", "2d6D", "D6D"] {
+            assert!(roll(input.to_string()).is_err(), "{}", input);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn d20_outcomes() {
+        let outcome = |input: &str, forced: &[i32]| roll_with_forced(input.to_string(), forced).unwrap().get_d20_outcome();
+        assert_eq!(Some(D20Outcome::AutoFail), outcome("1d20 + 30", &[1]));
+        assert_eq!(Some(D20Outcome::AutoSuccess), outcome("1d20 - 30", &[20]));
+        assert_eq!(Some(D20Outcome::Normal(17)), outcome("1d20 + 5", &[12]));
+        assert_eq!(Some(D20Outcome::Normal(17)), outcome("1d20 + 2d6", &[12, 2, 3]));
+        assert_eq!(Some(D20Outcome::AutoSuccess), outcome("2d20kh1 + 5", &[3, 20]));
+        assert_eq!(Some(D20Outcome::Normal(9)), outcome("1d20r1", &[1, 9]));
+        assert_eq!(Some(D20Outcome::AutoSuccess), outcome("1d20cap15", &[20]));
+        assert_eq!(None, outcome("2d20", &[1, 20]));
+        assert_eq!(None, outcome("1d12 + 5", &[1]));
+        assert_eq!(None, outcome("1d20 / 0", &[7]));
+        assert_eq!(Some(D20Outcome::AutoFail), outcome("1d20 / 0", &[1]));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn count_pool_successes_in_formula() {
+        let options = RollOptions { count_pool_successes: true, ..RollOptions::default() };
+        let roll = |input: &str, forced: &[i32]| {
+            let mut forced = ForcedRolls { forced: forced.iter(), fallback: thread_rng() };
+            roll_with_roller(input, &options, &mut forced).unwrap()
+        };
+
+        let rolls = roll("4d6>=5 + 2", &[5, 6, 2, 3]);
+        assert_eq!(4, rolls.get_result());
+        assert_eq!(Some(2), rolls.get_successes());
+        assert_eq!(2, rolls.get_groups()[0].subtotal);
+
+        // A success count mixes with the sum of other dice.
+        let rolls = roll("2d6>=4 + 1d4", &[4, 1, 3]);
+        assert_eq!(1 + 3, rolls.get_result());
+        assert_eq!(Some(1), rolls.get_successes());
+        assert_eq!(-2, roll("6d10>=7f1 * 2", &[7, 1, 1, 2, 3, 4]).get_result());
+        assert_eq!(2 + 1, roll("2(2d6>=4)", &[4, 5, 1, 6]).get_result());
+
+        // Without the option, a pool counts for the sum of its dice.
+        let sum = roll_with_forced("4d6>=5 + 2".to_string(), &[5, 6, 2, 3]).unwrap();
+        assert_eq!(5 + 6 + 2 + 3 + 2, sum.get_result());
+        assert_eq!(Some(2), sum.get_successes());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn check_result_without_panicking() {
+        let rolls = roll("1d6 / (2 - 2)".to_string()).unwrap();
+        assert_eq!(Err(RollError::DivisionByZero), rolls.get_result_checked());
+        assert_eq!(Ok(7), roll("3 + 4".to_string()).unwrap().get_result_checked());
+        assert_eq!(Err(RollError::Overflow), roll("2147483647 * 2".to_string()).unwrap().get_result_checked());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_grouped_thousands() {
+        for seed in 0..20 {
+            let rolls = roll_seeded("1,000 + 2d6", seed);
+            assert!(rolls.get_result() >= 1002 && rolls.get_result() <= 1012);
+        }
+        assert_eq!(9_999_999, roll("10,000,000 - 1".to_string()).unwrap().get_result());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_only_numbers() {
+        assert_eq!(Ok(14), roll_result("2 * (3 + 4)".to_string()));
+        assert_eq!(Ok(9), roll_sum("2 * (3 + 4)".to_string()));
+        assert_eq!(Err(RollError::DivisionByZero), roll_result("1d6 / 0".to_string()));
+        assert_eq!(Err(RollError::EmptyExpression), roll_sum(" ".to_string()));
+    }
+
+    #[test]
+    fn cap_and_floor_dice() {
+        for seed in 0..20 {
+            let rolls = roll_seeded("4d20cap15floor3", seed);
+            for r in &rolls.rolls {
+                assert!(r.result >= 3 && r.result <= 15);
+                assert_eq!(r.natural.clamp(3, 15), r.result);
+                if r.natural > 15 {
+                    assert!(rolls.get_rolls_string().contains(&format!("[15 ({})]", r.natural)));
+                }
+            }
+            assert_eq!(rolls.rolls.iter().map(|r| r.result).sum::<i32>(), rolls.get_result());
+
+            // Explosions are triggered by the face rolled, not the capped result.
+            let exploded = roll_seeded("3d6!cap4", seed);
+            let natural_sixes = exploded.rolls.iter().filter(|r| r.natural == 6).count();
+            assert_eq!(3 + natural_sixes, exploded.rolls.len());
+        }
+    }
+
+    #[test]
+    fn mark_dropped_dice() {
+        let mut rolls = DiceRolls::from_parts(
+            vec![
+                RollResult { sides: 6, result: 5 },
+                RollResult { sides: 6, result: 2 },
+                RollResult { sides: 6, result: 4 },
+            ],
+            vec!["11".to_string()],
+            vec!["3d6".to_string()],
+        )
+        .unwrap();
+        rolls.rolls[1].kept = false;
+        assert_eq!("d6 -> [5], d6 -> [~2~], d6 -> [4]", rolls.get_rolls_string());
+        assert_eq!(
+            vec![RollResult { sides: 6, result: 5 }, RollResult { sides: 6, result: 4 }],
+            rolls.get_kept_rolls()
+        );
+
+        for seed in 0..20 {
+            let kept = roll_seeded("4d6dl1", seed);
+            assert_eq!(1, kept.get_rolls_string().matches('~').count() / 2);
+            assert_eq!(3, kept.get_kept_rolls().len());
+            assert_eq!(kept.get_result(), kept.get_kept_rolls().iter().map(|r| r.result).sum::<i32>());
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn modify_each_die() {
+        for seed in 0..20 {
+            assert_eq!(roll_seeded("3d6 + 3", seed).get_result(), roll_seeded("3d6e1", seed).get_result());
+            assert_eq!(roll_seeded("3d6 - 3", seed).get_result(), roll_seeded("3d6e-1", seed).get_result());
+            let rolls = roll_seeded("4d6e-2floor1 - 1", seed);
+            for r in rolls.rolls.iter().filter(|r| r.sides == 6) {
+                assert_eq!((r.natural - 2).max(1), r.result);
+            }
+        }
+        assert_eq!(Err(RollError::Overflow), roll("1d6e2147483647".to_string()).map(|_| ()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_forced_faces() {
+        let rolls = roll_with_forced("2d20kh1 + 3d6!".to_string(), &[20, 1, 6, 6, 2, 3, 4]).unwrap();
+        assert_eq!(20 + 6 + 6 + 2 + 3 + 4, rolls.get_result());
+        assert_eq!(
+            "d20 -> [20], d20 -> [~1~], d6 -> [6], d6 -> [6], d6 -> [2], d6 -> [3], d6 -> [4]",
+            rolls.get_rolls_string()
+        );
+
+        // Once the forced faces run out, the remaining dice roll randomly.
+        let rolls = roll_with_forced("4d6".to_string(), &[6]).unwrap();
+        assert_eq!(6, rolls.rolls[0].result);
+        assert_eq!(4, rolls.rolls.len());
+        assert!(rolls.rolls[1..].iter().all(|r| r.result >= 1 && r.result <= 6));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn group_dice_by_term() {
+        let rolls = roll_with_forced("2d6 + 3d8 * 2".to_string(), &[4, 5, 2, 7, 1]).unwrap();
+        assert_eq!(
+            vec![
+                DiceGroup {
+                    notation: "2d6".to_string(),
+                    rolls: vec![RollResult { sides: 6, result: 4 }, RollResult { sides: 6, result: 5 }],
+                    subtotal: 9,
+                    tag: None,
+                },
+                DiceGroup {
+                    notation: "3d8".to_string(),
+                    rolls: vec![
+                        RollResult { sides: 8, result: 2 },
+                        RollResult { sides: 8, result: 7 },
+                        RollResult { sides: 8, result: 1 },
+                    ],
+                    subtotal: 10,
+                    tag: None,
+                },
+                DiceGroup {
+                    notation: "2".to_string(),
+                    rolls: vec![RollResult { sides: 0, result: 2 }],
+                    subtotal: 2,
+                    tag: None,
+                },
+            ],
+            rolls.get_groups()
+        );
+
+        let kept = roll_with_forced("(1d4)d6kh1".to_string(), &[2, 3, 6]).unwrap();
+        assert_eq!(3, kept.get_groups()[0].rolls.len());
+        assert_eq!(6, kept.get_groups()[0].subtotal);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_with_advantage_and_disadvantage() {
+        for _ in 0..20 {
+            let rolls = roll_with_advantage("1d20 + 5".to_string()).unwrap();
+            let d20s: Vec<&DiceRoll> = rolls.rolls.iter().filter(|r| r.sides == 20).collect();
+            assert_eq!(2, d20s.len());
+            assert_eq!(d20s[0].result.max(d20s[1].result) + 5, rolls.get_result());
+
+            let rolls = roll_with_disadvantage("d20 - 1".to_string()).unwrap();
+            assert_eq!(rolls.rolls[0].result.min(rolls.rolls[1].result) - 1, rolls.get_result());
+        }
+        assert_eq!(Err(RollError::MissingD20), roll_with_advantage("2d6".to_string()).map(|_| ()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_tens_dice() {
+        for seed in 0..20 {
+            let rolls = roll_seeded("d00 + d10", seed);
+            assert_eq!(0, rolls.rolls[0].result % 10);
+            assert!(rolls.get_result() >= 1 && rolls.get_result() <= 100);
+        }
+        assert_eq!(100, roll_with_forced("d00 + d10".to_string(), &[90, 10]).unwrap().get_result());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_opposed_formulas() {
+        let opposed = roll_opposed("12".to_string(), "2d6 + 20".to_string()).unwrap();
+        assert_eq!(OpposedOutcome::Defender(opposed.defender.get_result() - 12), opposed.outcome);
+        assert_eq!(OpposedOutcome::Attacker(3), roll_opposed("5".to_string(), "2".to_string()).unwrap().outcome);
+        assert_eq!(OpposedOutcome::Tie, roll_opposed("2 * 3".to_string(), "6".to_string()).unwrap().outcome);
+        assert!(roll_opposed("1d20".to_string(), "".to_string()).is_err());
+        assert_eq!(Err(RollError::Overflow), roll_opposed("2147483647".to_string(), "-5".to_string()).map(|_| ()));
+        assert_eq!(Err(RollError::Overflow), roll_opposed("-2147483647 - 1".to_string(), "0".to_string()).map(|_| ()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_one_sided_dice() {
+        let fixed = RollOptions { boolean_dice: false, ..RollOptions::default() };
+        for seed in 0..20 {
+            let rolls = roll_seeded("3d1", seed);
+            assert!(rolls.rolls.iter().all(|r| r.result == 0 || r.result == 1));
+            assert_eq!(3, roll_with_options("3d1".to_string(), &fixed).unwrap().get_result());
+        }
+        assert!(RollOptions::default().boolean_dice);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_unspaced_formulas() {
+        for seed in 0..20 {
+            assert_eq!(roll_seeded("2d6 + 1d4 - 3", seed).get_result(), roll_seeded("2d6+1d4-3", seed).get_result());
+            assert_eq!(roll_seeded("d% - d10 * 2", seed).get_result(), roll_seeded("d%-d10*2", seed).get_result());
+        }
+        assert_eq!(Err(RollError::InvalidFragment { fragment: "2d".to_string(), position: 0 }), roll("2d-3".to_string()).map(|_| ()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn count_dice_by_size() {
+        let rolls = roll("2d6 + 3d8 - 4".to_string()).unwrap();
+        let mut expected = BTreeMap::new();
+        expected.insert(6, 2);
+        expected.insert(8, 3);
+        assert_eq!(expected, rolls.get_dice_counts());
+
+        let kept = roll_with_forced("3d6!kh1 + 1d6".to_string(), &[6, 1, 2, 3, 4]).unwrap();
+        assert_eq!(Some(&5), kept.get_dice_counts().get(&6));
+        assert!(roll("5".to_string()).unwrap().get_dice_counts().is_empty());
+    }
+
+    #[test]
+    fn roll_digit_dice() {
+        for seed in 0..20 {
+            for r in &roll_seeded("3d66 + d666", seed) {
+                let digits = r.result.to_string();
+                assert_eq!(r.sides.to_string().len(), digits.len());
+                assert!(digits.chars().all(|c| ('1'..='6').contains(&c)));
+            }
+            let genuine = roll_seeded("d(66)", seed);
+            assert!(genuine.get_result() >= 1 && genuine.get_result() <= 66);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_with_given_rng() {
+        for seed in 0..20 {
+            let options = RollOptions { seed: Some(seed), ..RollOptions::default() };
+            let rolls = roll_with_options("4d6kh3 + 1d8".to_string(), &options).unwrap();
+            assert_eq!(roll_seeded("4d6kh3 + 1d8", seed).get_result(), rolls.get_result());
+        }
+    }
+
+    #[test]
+    fn roll_without_std() {
+        for seed in 0..50 {
+            let rolls = roll_seeded("4d6kh3 + 1d8 - 2", seed);
+            assert!((2..=24).contains(&rolls.get_result()));
+            assert_eq!(rolls.get_rolls_string(), roll_seeded("4d6kh3 + 1d8 - 2", seed).get_rolls_string());
+
+            let expression = parse_expression("(2d6 + 3) * 2 dc15").unwrap();
+            let rolls = evaluate_with_rng(&expression, &RollOptions::default(), &mut StdRng::seed_from_u64(seed)).unwrap();
+            assert!((10..=30).contains(&rolls.get_result()));
+            assert_eq!(Some(rolls.get_result() >= 15), rolls.succeeded());
+        }
+
+        let floor = RollOptions { rounding: RoundingMode::Floor, ..RollOptions::default() };
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(3, roll_with_rng("7 / 2", &floor, &mut rng).unwrap().get_result());
+        assert_eq!(Err(RollError::DivisionByZero), roll_with_rng("1d6 / 0", &floor, &mut rng).unwrap().get_result_checked());
+        assert_eq!(Err(RollError::MissingOperand("+".to_string())), roll_with_rng("2d6 +", &floor, &mut rng).map(|_| ()));
+        assert!(roll_with_rng("{4d6, 1d8}kh3 + 3(1d4)", &floor, &mut rng).is_ok());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn aggregate_repeated_rolls() {
+        assert_eq!(Ok(18), roll_aggregate("3".to_string(), 6, AggregateMode::Sum));
+        assert_eq!(Ok(3), roll_aggregate("3".to_string(), 6, AggregateMode::Average));
+        for _ in 0..20 {
+            let max = roll_aggregate("1d20".to_string(), 5, AggregateMode::Max).unwrap();
+            let min = roll_aggregate("1d20".to_string(), 5, AggregateMode::Min).unwrap();
+            let average = roll_aggregate("1d6".to_string(), 3, AggregateMode::Average).unwrap();
+            assert!((1..=20).contains(&max) && (1..=20).contains(&min));
+            assert!((1..=6).contains(&average));
+        }
+        assert_eq!(Err(RollError::EmptyExpression), roll_aggregate("1d6".to_string(), 0, AggregateMode::Max));
+        assert_eq!(Err(RollError::MissingOperator), roll_aggregate("(1d6)(2)".to_string(), 3, AggregateMode::Sum));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn report_error_positions() {
+        let error = roll("2d6 + @".to_string()).map(|_| ()).unwrap_err();
+        assert_eq!(RollError::InvalidFragment { fragment: "@".to_string(), position: 6 }, error);
+        assert_eq!(Some(6), error.position());
+
+        // Leading whitespace, modifiers and bracketed terms all point at the term in the input.
+        assert_eq!(Some(8), roll("  1d4 + 2d(1 - 1) dc10".to_string()).map(|_| ()).unwrap_err().position());
+        assert_eq!(Some(4), roll("1 + 3d8a".to_string()).map(|_| ()).unwrap_err().position());
+        assert_eq!(Some(1), roll(" (2d6 + 1".to_string()).map(|_| ()).unwrap_err().position());
+        assert_eq!(None, roll("2d6 +".to_string()).map(|_| ()).unwrap_err().position());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reject_empty_and_dangling_input() {
+        assert_eq!(Err(RollError::EmptyExpression), roll("".to_string()).map(|_| ()));
+        assert_eq!(Err(RollError::EmptyExpression), roll("   ".to_string()).map(|_| ()));
+        assert_eq!(Err(RollError::EmptyExpression), roll(" \t\n ".to_string()).map(|_| ()));
+        assert_eq!(
+            Err(RollError::MissingOperand("+".to_string())),
+            roll("+".to_string()).map(|_| ())
+        );
+        assert_eq!(
+            Err(RollError::MissingOperand("*".to_string())),
+            roll("2d6 *".to_string()).map(|_| ())
+        );
     }
 }
@@ -0,0 +1,141 @@
+//! Provides the settings that control how a formula is rolled and solved.
+
+/// How the result of a division is rounded to a whole number.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RoundingMode {
+    /// Round to the nearest whole number, with halves rounded away from zero.
+    #[default]
+    Nearest,
+    /// Round down, towards negative infinity.
+    Floor,
+    /// Round up, towards positive infinity.
+    Ceil,
+    /// Round towards zero, discarding any fractional part.
+    Truncate,
+}
+
+impl RoundingMode {
+    /// Returns `dividend / divisor` rounded according to this mode.
+    ///
+    /// # Panics
+    /// Panics if `divisor` is zero.
+    pub fn divide(self, dividend: i32, divisor: i32) -> i32 {
+        let quotient = dividend / divisor;
+        let remainder = dividend % divisor;
+        if remainder == 0 {
+            return quotient;
+        }
+
+        // The sign of the exact (fractional) result.
+        let negative = (remainder < 0) != (divisor < 0);
+        match self {
+            RoundingMode::Truncate => quotient,
+            RoundingMode::Floor => if negative { quotient - 1 } else { quotient },
+            RoundingMode::Ceil => if negative { quotient } else { quotient + 1 },
+            RoundingMode::Nearest => if 2 * remainder.abs() >= divisor.abs() {
+                if negative { quotient - 1 } else { quotient + 1 }
+            } else {
+                quotient
+            },
+        }
+    }
+}
+
+/// How the results of rolling a formula several times are combined by
+/// [roll_aggregate()](fn.roll_aggregate.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AggregateMode {
+    /// The total of all the results.
+    Sum,
+    /// The mean of the results, rounded to the nearest whole number as with `RoundingMode::Nearest`.
+    Average,
+    /// The highest result.
+    Max,
+    /// The lowest result.
+    Min,
+}
+
+/// Settings for [roll_with_options()](fn.roll_with_options.html).
+///
+/// `RollOptions::default()` rolls exactly as [roll()](fn.roll.html) does.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RollOptions {
+    /// How division results are rounded. Defaults to `RoundingMode::Nearest`.
+    pub rounding: RoundingMode,
+    /// A seed for the random number generator, so that the same formula rolled with the
+    /// same seed gives the same dice. Defaults to None, which rolls with `thread_rng()`.
+    pub seed: Option<u64>,
+    /// Whether one-sided dice such as `1d1` are boolean dice, rolling `0` or `1`. When
+    /// false, they always show `1`, as a one-sided die would. Defaults to true.
+    pub boolean_dice: bool,
+    /// The most operations a single roll may take, where each die rolled (including any
+    /// rerolls and explosions) and each repetition of a repeated formula is an operation.
+    /// Rolls that would take more return `RollError::ComputationLimit`, to guard against
+    /// input such as `1000(1000(1000d6))`. Defaults to `10_000`.
+    pub max_operations: u32,
+    /// The most follow-up dice a single exploding or penetrating die may add. Defaults to `100`.
+    pub max_explosion_depth: i32,
+    /// The most that the follow-up dice of a single exploding or penetrating die may add
+    /// to it. The die stops exploding once they have added this much, and the last of them
+    /// counts for no more than what was left. Defaults to None, for no limit besides
+    /// `max_explosion_depth`.
+    pub max_explosion_total: Option<i32>,
+    /// Whether percentile dice written `d%` are rolled as a tens die and a ones die, which
+    /// are shown separately, as in `70 + 3 = 73`. Either way they roll from `1` to `100`,
+    /// with `00` and `0` read as `100`. Defaults to false, showing a single number.
+    pub percentile_breakdown: bool,
+    /// Whether standard and percentile dice are zero-indexed, rolling from `0` up to one
+    /// less than their sides, so that `1d10` rolls `0` to `9` and `d%` rolls `0` to `99`.
+    /// They then explode on one less than their sides. One-sided dice, average dice, `d00`,
+    /// `d66` and `d%` rolled with `percentile_breakdown` are rolled as usual. Defaults to
+    /// false, rolling from `1`.
+    pub zero_indexed_dice: bool,
+    /// The lowest result a roll may have, as with damage that can't drop below `1` however
+    /// large a penalty is taken from it. `1d4 - 3` rolling a `1` then has a result of `1`
+    /// rather than `-2`. Only the result of the whole formula (and of each repetition of a
+    /// repeated formula) is raised; the dice and the sum of rolls are unchanged. Defaults
+    /// to None, for no minimum.
+    pub floor_result_at: Option<i32>,
+    /// The sides of dice written without any, so that `d` rolls `1d6` and `3d` rolls `3d6`
+    /// with a default of `6`. Defaults to None, which rejects such dice as invalid.
+    pub default_sides: Option<i32>,
+    /// Whether each dice pool counts for its net successes in the formula, rather than for
+    /// the sum of its dice, so that `4d6>=5 + 2` adds `2` to the successes. Other terms
+    /// count as usual, so `2d6>=4 + 1d4` adds the successes of the `2d6` to the `d4`.
+    /// The successes read with `get_successes()` are those of the pools alone either way.
+    /// Defaults to false.
+    pub count_pool_successes: bool,
+}
+
+impl Default for RollOptions {
+    fn default() -> RollOptions {
+        RollOptions {
+            rounding: RoundingMode::default(),
+            seed: None,
+            boolean_dice: true,
+            max_operations: 10_000,
+            max_explosion_depth: 100,
+            max_explosion_total: None,
+            percentile_breakdown: false,
+            zero_indexed_dice: false,
+            floor_result_at: None,
+            default_sides: None,
+            count_pool_successes: false,
+        }
+    }
+}
+
+#[test]
+fn divide_with_rounding() {
+    assert_eq!(3, RoundingMode::Nearest.divide(5, 2));
+    assert_eq!(-3, RoundingMode::Nearest.divide(-5, 2));
+    assert_eq!(2, RoundingMode::Nearest.divide(7, 3));
+    assert_eq!(2, RoundingMode::Floor.divide(5, 2));
+    assert_eq!(-3, RoundingMode::Floor.divide(-5, 2));
+    assert_eq!(-3, RoundingMode::Floor.divide(5, -2));
+    assert_eq!(3, RoundingMode::Ceil.divide(5, 2));
+    assert_eq!(-2, RoundingMode::Ceil.divide(-5, 2));
+    assert_eq!(2, RoundingMode::Truncate.divide(5, 2));
+    assert_eq!(-2, RoundingMode::Truncate.divide(-5, 2));
+    assert_eq!(4, RoundingMode::Floor.divide(8, 2));
+}
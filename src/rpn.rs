@@ -1,8 +1,19 @@
 //! Provides facilities for parsing and solving reverse Polish notation dice specifications.
-use tokens::match_token;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use error::RollError;
+use options::{RollOptions, RoundingMode};
+use tokens::{is_operator_symbol, match_token, tokenize, Token};
 
 /// Returns a Vector of Strings with each element containing a token or an operator in postfix (rpn) format.
 ///
+/// A bracket without a partner returns `RollError::UnbalancedParentheses`, pointing at the
+/// character offset of the offending bracket within `input_formula`, and an operator
+/// straight after another, as in `2d6 + * 3`, returns `RollError::UnexpectedOperator`.
+/// Values and operators must take turns, so an operator at the start or end of the formula
+/// or of a bracket, as in `*(2)(3)`, returns `RollError::MissingOperand`, and two values side
+/// by side, as in `(2)(3)`, return `RollError::MissingOperator`.
+///
 /// # Arguments
 /// * `input_formula` - A string that provides the notation to work off.
 ///
@@ -11,93 +22,209 @@ use tokens::match_token;
 /// * `3 + 4 * 6` -> `["3", "4", "6", "*", "+"]`
 /// * `2d4 + d6 + d4` -> `["2d4", "d6", "d4", "+", "+"]`
 /// * `xv * (ab + dc)` -> `["xv", "ab", "dc", "+", "*"]`
-pub fn parse_into_rpn(input_formula: &str) -> Vec<String> {
-    let formula = input_formula.replace(" ", "").replace("_", "");
-    let mut formula_vector: Vec<String> = Vec::new();
-    let mut active_segment = String::new();
-    let mut operator_stack: Vec<String> = Vec::new();
-    let mut lorb = false;
+/// * `(1d4)d6 + 2d(1d4)` -> `["(1d4)d6", "2d(1d4)", "+"]`
+pub fn parse_into_rpn(input_formula: &str) -> Result<Vec<String>, RollError> {
+    Ok(parse_tokens_into_rpn(input_formula)?.into_iter().map(|token| token.text).collect())
+}
+
+/// Returns the tokens of a formula in postfix (rpn) format, each keeping the character
+/// offset it was found at. See [parse_into_rpn()](fn.parse_into_rpn.html).
+pub(crate) fn parse_tokens_into_rpn(input_formula: &str) -> Result<Vec<Token>, RollError> {
+    rearrange_into_rpn(tokenize(input_formula))
+}
 
-    for c in formula.chars() {
-        let cs = c.to_string();
-        let precedence = match_token(cs.as_ref());
+/// Returns tokens that are already split up, in the order they were written, rearranged
+/// into postfix (rpn) format.
+pub(crate) fn rearrange_into_rpn(tokens: Vec<Token>) -> Result<Vec<Token>, RollError> {
+    let mut open_brackets: Vec<usize> = Vec::new();
+    let mut formula_vector: Vec<Token> = Vec::new();
+    let mut operator_stack: Vec<Token> = Vec::new();
+    // The operator last read while a value is still expected, or None at the start of
+    // the formula or of a bracket, where a value is expected without any operator.
+    let mut pending_operator: Option<String> = None;
+    let mut expect_value = true;
+
+    for token in tokens {
+        let precedence = match token.text.as_ref() {
+            // An operator written with a letter, such as `x`, is a value where one is expected.
+            text if expect_value && !is_operator_symbol(text) && match_token(text) > 0 => 0,
+            text => match_token(text),
+        };
 
         match precedence {
             // Current token is an operator token
-            p if p > 0 => if active_segment.len() > 0 {
-                formula_vector.push(active_segment.clone());
-                active_segment = String::new();
+            p if p > 0 => {
+                if expect_value {
+                    return Err(match pending_operator {
+                        Some(_) => RollError::UnexpectedOperator { operator: token.text, position: token.position },
+                        None => RollError::MissingOperand(token.text),
+                    });
+                }
+                expect_value = true;
+                pending_operator = Some(token.text.clone());
+                // Operators of the same binding are left-associative, so an earlier one
+                // is applied first, as in `(10 - 2) + 3` and `(12 * 2) / 3`.
                 while let Some(top) = operator_stack.pop() {
-                    if match_token(top.as_ref()) >= precedence {
-                        formula_vector.push(top.to_string());
+                    if binding(match_token(top.text.as_ref())) >= binding(precedence) {
+                        formula_vector.push(top);
                     } else {
                         operator_stack.push(top);
                         break;
                     }
                 }
-                operator_stack.push(cs);
-            } else if lorb {
-                operator_stack.push(cs);
-            } else {
-                active_segment.push(c);
-            },
+                operator_stack.push(token);
+            }
             // Current token is a left bracket token
-            p if p == -1 => {
-                lorb = false;
-                operator_stack.push(cs);
+            -1 => {
+                if !expect_value {
+                    return Err(RollError::MissingOperator);
+                }
+                pending_operator = None;
+                open_brackets.push(token.position);
+                operator_stack.push(token);
             }
             // Current token is a right bracket token
-            p if p == -2 => {
-                if active_segment.len() > 0 {
-                    formula_vector.push(active_segment.clone());
-                    active_segment = String::new();
-                    lorb = true;
+            -2 => {
+                if open_brackets.pop().is_none() {
+                    return Err(RollError::UnbalancedParentheses { position: token.position, parenthesis: ')' });
+                }
+                if let Some(operator) = pending_operator.take() {
+                    return Err(RollError::MissingOperand(operator));
                 }
+                expect_value = false;
                 while let Some(top) = operator_stack.pop() {
-                    if match_token(top.as_ref()) == -1 {
+                    if match_token(top.text.as_ref()) == -1 {
                         break;
                     }
-                    formula_vector.push(top.to_string());
+                    formula_vector.push(top);
                 }
             }
             // Current token is a standard token
             _ => {
-                lorb = false;
-                active_segment.push(c);
+                if !expect_value {
+                    return Err(RollError::MissingOperator);
+                }
+                expect_value = false;
+                pending_operator = None;
+                formula_vector.push(token);
             }
         }
     }
 
-    if active_segment.len() > 0 {
-        formula_vector.push(active_segment);
+    if let Some(position) = open_brackets.pop() {
+        return Err(RollError::UnbalancedParentheses { position, parenthesis: '(' });
+    }
+    if let Some(operator) = pending_operator {
+        return Err(RollError::MissingOperand(operator));
     }
 
     while let Some(top) = operator_stack.pop() {
-        formula_vector.push(top.to_string());
+        formula_vector.push(top);
     }
 
-    return formula_vector;
+    Ok(formula_vector)
+}
+
+/// Returns how tightly an operator of the given token precedence binds its operands.
+/// Multiplication and division bind equally tightly, as do addition and subtraction.
+fn binding(precedence: i32) -> i32 {
+    match precedence {
+        3 | 4 => 2,
+        1 | 2 => 1,
+        p => p,
+    }
 }
 
 #[test]
 fn parse_rpn_formula() {
-    assert_eq!(vec!["3", "4", "+"], parse_into_rpn("3 + 4"));
+    assert_eq!(vec!["3", "4", "+"], parse_into_rpn("3 + 4").unwrap());
     assert_eq!(
         vec!["3", "4", "2", "1", "−", "×", "+"],
-        parse_into_rpn("3 + 4 × (2 − 1)")
+        parse_into_rpn("3 + 4 × (2 − 1)").unwrap()
     );
     assert_eq!(
         vec!["2", "1", "−", "3", "×", "4", "+"],
-        parse_into_rpn("(2 − 1) × 3 + 4")
+        parse_into_rpn("(2 − 1) × 3 + 4").unwrap()
     );
-    assert_eq!(vec!["x", "y", "+"], parse_into_rpn("x + y"));
+    assert_eq!(vec!["x", "y", "+"], parse_into_rpn("x + y").unwrap());
     assert_eq!(
         vec!["ab", "cd", "ef", "gh", "−", "×", "+"],
-        parse_into_rpn("ab + cd × (ef − gh)")
+        parse_into_rpn("ab + cd × (ef − gh)").unwrap()
     );
     assert_eq!(
         vec!["2d5", "1d6", "−", "3d6", "×", "2d12", "+"],
-        parse_into_rpn("(2d5 − 1d6) × 3d6 + 2d12")
+        parse_into_rpn("(2d5 − 1d6) × 3d6 + 2d12").unwrap()
+    );
+    assert_eq!(
+        vec!["(1d4)d6", "2d(1d4+(2))", "+"],
+        parse_into_rpn("(1d4)d6 + 2d(1d4 + (2))").unwrap()
+    );
+    assert_eq!(vec!["1d4", "6", "*"], parse_into_rpn("(1d4) * 6").unwrap());
+    assert_eq!(vec!["2", "3", "*", "4", "+"], parse_into_rpn("2 * (3) + 4").unwrap());
+    assert_eq!(vec!["2", "-3", "+"], parse_into_rpn("(2) + -3").unwrap());
+    assert_eq!(vec!["2d6", "3", "+"], parse_into_rpn("+2d6 + 3").unwrap());
+    assert_eq!(
+        Err(RollError::UnexpectedOperator { operator: "+".to_string(), position: 5 }),
+        parse_into_rpn("2d6 ++ 3")
+    );
+    assert_eq!(
+        Err(RollError::UnexpectedOperator { operator: "*".to_string(), position: 5 }),
+        parse_into_rpn("2d6 +* 3")
+    );
+}
+
+#[test]
+fn reject_values_and_operators_out_of_turn() {
+    let missing = |operator: &str| Err(RollError::MissingOperand(operator.to_string()));
+    assert_eq!(missing("*"), parse_into_rpn("*(2)(3)"));
+    assert_eq!(missing("-"), parse_into_rpn("-(2)d6"));
+    assert_eq!(missing("-"), parse_into_rpn("-(1d4)d6"));
+    assert_eq!(missing("+"), parse_into_rpn("(2 +) * 3"));
+    assert_eq!(missing("+"), parse_into_rpn("2d6 +"));
+    assert_eq!(Err(RollError::MissingOperator), parse_into_rpn("(2)(3)+"));
+    assert_eq!(Err(RollError::MissingOperator), parse_into_rpn("(2)(3)"));
+    assert_eq!(Err(RollError::MissingOperator), parse_into_rpn("(2d6) 3"));
+    // An `x` where a value is expected is a value, and an operator everywhere else.
+    assert_eq!(vec!["2", "x", "+"], parse_into_rpn("2 + x").unwrap());
+    assert_eq!(vec!["2", "3", "x"], parse_into_rpn("2 x 3").unwrap());
+}
+
+#[test]
+fn parse_unspaced_formulas() {
+    let pairs = [
+        ("2d6+1d4", "2d6 + 1d4"),
+        ("2d6+1d4-3", "2d6 + 1d4 - 3"),
+        ("d%+d10*2", "d% + d10 * 2"),
+        ("4d6kh3*(1d4-1)/2", "4d6kh3 * (1d4 - 1) / 2"),
+        ("(1d4)d6+2d(1d4)-d8!", "(1d4)d6 + 2d(1d4) - d8!"),
+        ("3×-2+1d6÷2", "3 × -2 + 1d6 ÷ 2"),
+        ("{4d6,1d8}kh3-1", "{4d6, 1d8}kh3 - 1"),
+    ];
+    for &(unspaced, spaced) in pairs.iter() {
+        assert_eq!(parse_into_rpn(spaced), parse_into_rpn(unspaced));
+    }
+    assert_eq!(vec!["2d6", "1d4", "+", "3", "-"], parse_into_rpn("2d6+1d4-3").unwrap());
+    // A `-` straight after the `d` ends the dice, rather than making their sides negative.
+    assert_eq!(vec!["2d", "3", "-"], parse_into_rpn("2d-3").unwrap());
+}
+
+#[test]
+fn reject_unbalanced_parentheses() {
+    assert_eq!(
+        Err(RollError::UnbalancedParentheses { position: 0, parenthesis: '(' }),
+        parse_into_rpn("(2d6 + 1")
+    );
+    assert_eq!(
+        Err(RollError::UnbalancedParentheses { position: 3, parenthesis: ')' }),
+        parse_into_rpn("2d6) + 1")
+    );
+    assert_eq!(
+        Err(RollError::UnbalancedParentheses { position: 2, parenthesis: ')' }),
+        parse_into_rpn("())(")
+    );
+    assert_eq!(
+        Err(RollError::UnbalancedParentheses { position: 6, parenthesis: '(' }),
+        parse_into_rpn("(1) × (((2))")
     );
 }
 
@@ -105,42 +232,143 @@ fn parse_rpn_formula() {
 ///
 /// # Arguments
 /// * `formula` - A Vector of Strings that provides the postfix formatted notation to work off.
-/// See [rfyl::parse_into_rpn()](fn.parse_into_rpn.html) for more details.
+///   See [rfyl::parse_into_rpn()](fn.parse_into_rpn.html) for more details.
 ///
 /// # Example values
 ///
 /// * `["3", "4", "6", "*", "+"]` -> `27`
 pub fn solve_rpn_formula(formula: Vec<String>) -> i32 {
+    solve_rpn_formula_with_rounding(formula, RoundingMode::Nearest)
+}
+
+/// Returns an i32 as the result of a postfix (rpn) formula, rounding the result
+/// of each division as specified.
+///
+/// # Arguments
+/// * `formula` - A Vector of Strings that provides the postfix formatted notation to work off.
+/// * `rounding` - How each division result is rounded to a whole number.
+///
+/// # Example values
+///
+/// * `["7", "2", "/"]`, `RoundingMode::Floor` -> `3`
+/// * `["7", "2", "/"]`, `RoundingMode::Ceil` -> `4`
+pub fn solve_rpn_formula_with_rounding(formula: Vec<String>, rounding: RoundingMode) -> i32 {
+    match solve_rpn_formula_checked(&formula, rounding) {
+        Ok(total) => total,
+        Err(error) => panic!("Cannot solve `{}`: {}", formula.join(" "), error),
+    }
+}
+
+/// Returns the result of a postfix (rpn) formula, or an error where
+/// [solve_rpn_formula()](fn.solve_rpn_formula.html) would panic.
+///
+/// An operator without both operands returns `RollError::MissingOperand`, values left
+/// without an operator return `RollError::MissingOperator`, and an empty formula returns
+/// `RollError::EmptyExpression`. Dividing by zero returns `RollError::DivisionByZero`, and
+/// a result too large for an i32 returns `RollError::Overflow`. A term that is neither a
+/// whole number nor an operator, such as unrolled dice, returns `RollError::InvalidTerm`.
+///
+/// # Arguments
+/// * `formula` - A slice of Strings that provides the postfix formatted notation to work off.
+/// * `rounding` - How each division result is rounded to a whole number.
+///
+/// # Example values
+///
+/// * `["7", "2", "/"]`, `RoundingMode::Floor` -> `Ok(3)`
+/// * `["7", "0", "/"]`, `RoundingMode::Floor` -> `Err(RollError::DivisionByZero)`
+pub fn solve_rpn_formula_checked(formula: &[String], rounding: RoundingMode) -> Result<i32, RollError> {
     let mut working_stack: Vec<i32> = Vec::new();
-    let mut total: i32 = 0;
-    for e in formula.iter() {
-        if e.parse::<i32>().is_ok() {
-            working_stack.push(e.parse::<i32>().unwrap());
-        } else {
-            if let Some(a) = working_stack.pop() {
-                if let Some(b) = working_stack.pop() {
-                    match match_token(e) {
-                        4 => {
-                            if a == 0 {panic!("Divide by zero: `{} / {}` is undefined", b, a);}
-                            working_stack.push((b as f32 / a as f32).round() as i32)
-                        },
-                        3 => working_stack.push(b * a),
-                        2 => working_stack.push(b + a),
-                        1 => working_stack.push(b - a),
-                        _ => panic!("Invalid operator: `{}`", e),
-                    }
-                } else {
-                    panic!("Right hand token in evaluation doesn't exist");
+    for e in formula {
+        if let Ok(value) = e.parse::<i32>() {
+            working_stack.push(value);
+            continue;
+        }
+        if match_token(e) <= 0 {
+            return Err(RollError::InvalidTerm(e.to_string()));
+        }
+
+        let (a, b) = match (working_stack.pop(), working_stack.pop()) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Err(RollError::MissingOperand(e.to_string())),
+        };
+        let value = match match_token(e) {
+            4 => {
+                if a == 0 {
+                    return Err(RollError::DivisionByZero);
                 }
-            } else {
-                panic!("Left hand token in evaluation doesn't exist");
+                b.checked_div(a).map(|_| rounding.divide(b, a))
             }
+            3 => b.checked_mul(a),
+            2 => b.checked_add(a),
+            _ => b.checked_sub(a),
+        };
+        working_stack.push(value.ok_or(RollError::Overflow)?);
+    }
+
+    match working_stack.len() {
+        0 => Err(RollError::EmptyExpression),
+        1 => Ok(working_stack[0]),
+        _ => Err(RollError::MissingOperator),
+    }
+}
+
+/// Returns the result of a postfix (rpn) formula solved with the provided options, or an
+/// error as with [solve_rpn_formula_checked()](fn.solve_rpn_formula_checked.html).
+///
+/// # Arguments
+/// * `formula` - A slice of Strings that provides the postfix formatted notation to work off.
+/// * `options` - The settings to solve with. See [RollOptions](../struct.RollOptions.html).
+///
+/// # Example values
+///
+/// * `["7", "2", "/"]`, `RollOptions::default()` -> `Ok(4)`
+pub fn solve_rpn_formula_with_options(formula: &[String], options: &RollOptions) -> Result<i32, RollError> {
+    solve_rpn_formula_checked(formula, options.rounding)
+}
+
+/// Checks that a postfix (rpn) formula can be solved, without solving it.
+///
+/// Every operator must have two operands available, and exactly one value must
+/// remain once all operators have been applied.
+///
+/// # Arguments
+/// * `formula` - A slice of Strings that provides the postfix formatted notation to check.
+///
+/// # Example values
+///
+/// * `["3", "4", "+"]` -> `Ok(())`
+/// * `["+"]` -> `Err(RollError::MissingOperand("+"))`
+pub fn validate_rpn_formula(formula: &[String]) -> Result<(), RollError> {
+    let mut depth = 0;
+    for e in formula {
+        if match_token(e) > 0 {
+            if depth < 2 {
+                return Err(RollError::MissingOperand(e.to_string()));
+            }
+            depth -= 1;
+        } else {
+            depth += 1;
         }
     }
-    if let Some(t) = working_stack.pop() {
-        total = t;
+    match depth {
+        0 => Err(RollError::EmptyExpression),
+        1 => Ok(()),
+        _ => Err(RollError::MissingOperator),
     }
-    return total;
+}
+
+#[test]
+fn validate_rpn() {
+    assert_eq!(Ok(()), validate_rpn_formula(&["3".to_string(), "4".to_string(), "+".to_string()]));
+    assert_eq!(Err(RollError::EmptyExpression), validate_rpn_formula(&[]));
+    assert_eq!(
+        Err(RollError::MissingOperand("+".to_string())),
+        validate_rpn_formula(&["+".to_string()])
+    );
+    assert_eq!(
+        Err(RollError::MissingOperator),
+        validate_rpn_formula(&["3".to_string(), "4".to_string()])
+    );
 }
 
 #[test]
@@ -168,3 +396,63 @@ fn solve_rpn() {
         ])
     );
 }
+
+#[test]
+fn solve_chains_left_to_right() {
+    let solve = |input: &str| solve_rpn_formula(parse_into_rpn(input).unwrap());
+    assert_eq!(vec!["10", "2", "-", "3", "-"], parse_into_rpn("10-2-3").unwrap());
+    assert_eq!(5, solve("10-2-3"));
+    assert_eq!(10, solve("100/5/2"));
+    assert_eq!(18, solve("20-4/2"));
+    assert_eq!(11, solve("10 - 2 + 3"));
+    assert_eq!(8, solve("12 * 2 / 3"));
+    assert_eq!(18, solve("12 / 2 * 3"));
+    assert_eq!(4, solve("10 − 3 − 2 − 1"));
+    assert_eq!(1, solve("24 ÷ 4 ÷ 3 ÷ 2"));
+    assert_eq!(vec!["2", "3", "4", "*", "+"], parse_into_rpn("2 + 3 * 4").unwrap());
+}
+
+#[test]
+fn solve_rpn_with_rounding() {
+    let formula = vec!["7".to_string(), "2".to_string(), "/".to_string()];
+    assert_eq!(4, solve_rpn_formula(formula.clone()));
+    assert_eq!(4, solve_rpn_formula_with_rounding(formula.clone(), RoundingMode::Nearest));
+    assert_eq!(3, solve_rpn_formula_with_rounding(formula.clone(), RoundingMode::Floor));
+    assert_eq!(4, solve_rpn_formula_with_rounding(formula.clone(), RoundingMode::Ceil));
+    assert_eq!(3, solve_rpn_formula_with_rounding(formula, RoundingMode::Truncate));
+}
+
+#[test]
+fn solve_rpn_with_options() {
+    let formula = vec!["7".to_string(), "2".to_string(), "/".to_string()];
+    assert_eq!(Ok(4), solve_rpn_formula_with_options(&formula, &RollOptions::default()));
+    let floor = RollOptions { rounding: RoundingMode::Floor, ..RollOptions::default() };
+    assert_eq!(Ok(3), solve_rpn_formula_with_options(&formula, &floor));
+}
+
+#[test]
+fn solve_rpn_checked() {
+    let formula = |terms: &[&str]| terms.iter().map(|t| t.to_string()).collect::<Vec<String>>();
+    assert_eq!(Ok(3), solve_rpn_formula_checked(&formula(&["7", "2", "/"]), RoundingMode::Floor));
+    assert_eq!(
+        Err(RollError::DivisionByZero),
+        solve_rpn_formula_checked(&formula(&["7", "0", "/"]), RoundingMode::Nearest)
+    );
+    assert_eq!(
+        Err(RollError::MissingOperand("+".to_string())),
+        solve_rpn_formula_checked(&formula(&["7", "+"]), RoundingMode::Nearest)
+    );
+    assert_eq!(
+        Err(RollError::MissingOperator),
+        solve_rpn_formula_checked(&formula(&["7", "2"]), RoundingMode::Nearest)
+    );
+    assert_eq!(Err(RollError::EmptyExpression), solve_rpn_formula_checked(&[], RoundingMode::Nearest));
+    assert_eq!(
+        Err(RollError::Overflow),
+        solve_rpn_formula_checked(&formula(&["2147483647", "1", "+"]), RoundingMode::Nearest)
+    );
+    assert_eq!(
+        Err(RollError::InvalidTerm("2d6".to_string())),
+        solve_rpn_formula_checked(&formula(&["2d6", "3", "+"]), RoundingMode::Nearest)
+    );
+}
@@ -1,15 +1,339 @@
+//! Provides facilities for splitting a formula into tokens.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
 pub fn match_token(token: &str) -> i32 {
     match token {
-        "/" => return 4,
-        "÷" => return 4,
-        "*" => return 3,
-        "×" => return 3,
-        "+" => return 2,
-        "−" => return 1,
-        "-" => return 1,
-        "(" => return -1,
-        ")" => return -2,
-        "%" => return -3,
-        _ => return 0,
-    }
-}
\ No newline at end of file
+        "/" => 4,
+        "÷" => 4,
+        "*" => 3,
+        "×" => 3,
+        "x" => 3,
+        "X" => 3,
+        "+" => 2,
+        "−" => 1,
+        "-" => 1,
+        "(" => -1,
+        ")" => -2,
+        "%" => -3,
+        _ => 0,
+    }
+}
+
+/// Returns whether `token` is an operator written with a symbol, such as `+` or `×`, rather
+/// than with a letter that could also be part of a value, as `x` is.
+pub(crate) fn is_operator_symbol(token: &str) -> bool {
+    match_token(token) > 0 && token != "x" && token != "X"
+}
+
+/// One of the arithmetic operators of a formula.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl Operator {
+    /// Returns the symbol the operator is written with in a formula, e.g. `+`.
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+        }
+    }
+
+    /// Returns the operator written with `symbol`, including the alternative symbols
+    /// such as `×` and `÷`.
+    pub(crate) fn from_symbol(symbol: &str) -> Option<Operator> {
+        match match_token(symbol) {
+            4 => Some(Operator::Divide),
+            3 => Some(Operator::Multiply),
+            2 => Some(Operator::Add),
+            1 => Some(Operator::Subtract),
+            _ => None,
+        }
+    }
+}
+
+/// A single element of a postfix (rpn) formula whose dice have been rolled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RpnToken {
+    /// An operator, applied to the two values before it.
+    Operator(Operator),
+    /// A constant, or the total of some dice.
+    Value(i32),
+}
+
+/// Returns the byte offset of the bracket closing the `(` at the start of `input`,
+/// or None if it is never closed.
+pub fn find_closing_bracket(input: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in input.char_indices() {
+        match match_token(c.to_string().as_ref()) {
+            -1 => depth += 1,
+            -2 => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the byte offset of the first `target` in `input` outside of any brackets or
+/// braces, or None if there isn't one.
+pub(crate) fn find_top_level(input: &str, target: char) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth -= 1,
+            c if c == target && depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the parts of `input` between each `separator` outside of any brackets or braces,
+/// along with the character offset each part starts at.
+///
+/// # Example values
+///
+/// * `1d20 + 5; 1d6 + 2`, `;` -> `[(0, "1d20 + 5"), (9, " 1d6 + 2")]`
+/// * `1d20 / (1d6 / 2)`, `/` -> `[(0, "1d20 "), (6, " (1d6 / 2)")]`
+#[cfg(feature = "std")]
+pub fn split_top_level(input: &str, separator: char) -> Vec<(usize, &str)> {
+    let mut parts: Vec<(usize, &str)> = Vec::new();
+    let mut depth = 0;
+    let mut start = (0, 0);
+    for (n, (i, c)) in input.char_indices().enumerate() {
+        match c {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push((start.0, &input[start.1..i]));
+                start = (n + 1, i + c.len_utf8());
+            }
+            _ => {}
+        }
+    }
+    parts.push((start.0, &input[start.1..]));
+    parts
+}
+
+/// A single token of a formula, such as `2d6`, `+` or `(`.
+pub struct Token {
+    pub text: String,
+    /// The character offset of the start of the token in the original input.
+    pub position: usize,
+}
+
+/// Splits a formula into its values, operators and brackets, in the order they appear.
+///
+/// Whitespace and underscores are ignored. A minus sign that appears where a value is
+/// expected, such as the `-` of `2 * -3`, is kept as the sign of the value that follows.
+/// A plus sign at the start of the formula or of a bracket, as in `+2d6`, is dropped, while
+/// one straight after another operator, as in `2d6 ++ 3`, is kept as an operator of its own,
+/// as are `*`, `/`, `×` and `÷` there. Brackets supplying the count or sides of some dice,
+/// as in `(1d4)d6` or `2d(1d4)`, are kept whole as part of the dice. So are sets of terms
+/// in braces, as in `{4d6, 1d8}kh3`, and brackets repeated by the number before them, as
+/// in `3(1d6 + 2)`.
+///
+/// # Example values
+///
+/// * `2d6 + -3` -> `["2d6", "+", "-3"]`
+/// * `+2d6 ++ 3` -> `["2d6", "+", "+", "3"]`
+/// * `(1d4)d6 * (2)` -> `["(1d4)d6", "*", "(", "2", ")"]`
+/// * `{4d6, 1d8 - 1}kh3 + 2` -> `["{4d6,1d8-1}kh3", "+", "2"]`
+/// * `3(1d6 + 2) + 1` -> `["3(1d6+2)", "+", "1"]`
+/// * `2d6×3÷2` -> `["2d6", "×", "3", "÷", "2"]`
+/// * `1d20>=15 ? 2d6 : 0` -> `["1d20>=15?2d6:0"]`
+/// * `(1d20>=15 ? 2d6 : 0) + 3` -> `["(1d20>=15?2d6:0)", "+", "3"]`
+pub fn tokenize(input_formula: &str) -> Vec<Token> {
+    // The position of each remaining character in the original input is kept for
+    // reporting errors.
+    let (offsets, formula): (Vec<usize>, String) = input_formula
+        .chars()
+        .enumerate()
+        .filter(|&(_, c)| c != ' ' && c != '_')
+        .unzip();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut active_segment = String::new();
+    let mut active_position = 0;
+    let mut expect_value = true;
+    let mut skip_to = 0;
+
+    // A conditional binds more loosely than any operator, so it takes in the whole formula.
+    if find_top_level(&formula, '?').is_some() {
+        return vec![Token {
+            text: formula,
+            position: offsets[0],
+        }];
+    }
+
+    for (n, (i, c)) in formula.char_indices().enumerate() {
+        if i < skip_to {
+            continue;
+        }
+        if active_segment.is_empty() {
+            active_position = offsets[n];
+        }
+
+        // A set of terms in braces is kept whole, along with any modifiers after it.
+        if c == '{' {
+            if let Some(close) = formula[i..].find('}') {
+                active_segment.push_str(&formula[i..i + close + 1]);
+                skip_to = i + close + 1;
+                continue;
+            }
+        }
+
+        // A tag after a term, as in `2d6[fire]`, is kept with the term, even where
+        // it has a `-` or an `x` in it.
+        if c == '[' && !active_segment.is_empty() {
+            if let Some(close) = formula[i..].find(']') {
+                active_segment.push_str(&formula[i..i + close + 1]);
+                skip_to = i + close + 1;
+                continue;
+            }
+        }
+
+        if c == '(' {
+            if let Some(close) = find_closing_bracket(&formula[i..]) {
+                let end = i + close + 1;
+                let count = active_segment.trim_start_matches(['-', '+']);
+                let repeat = !count.is_empty() && count.chars().all(|c| c.is_ascii_digit());
+                let conditional = find_top_level(&formula[i + 1..end - 1], '?').is_some();
+                if active_segment.ends_with(['d', 'D'])
+                    || repeat
+                    || conditional
+                    || (active_segment.is_empty() && formula[end..].starts_with(['d', 'D']))
+                {
+                    active_segment.push_str(&formula[i..end]);
+                    skip_to = end;
+                    continue;
+                }
+            }
+        }
+
+        // The sign of a per-die modifier, as in `3d6e-1`, belongs to the dice.
+        if (c == '-' || c == '−')
+            && active_segment.contains(['d', 'D'])
+            && active_segment.ends_with('e')
+            && formula[i + c.len_utf8()..].starts_with(|n: char| n.is_ascii_digit())
+        {
+            active_segment.push('-');
+            continue;
+        }
+
+        let cs = c.to_string();
+        let after_operator = tokens.last().map_or(false, |t| is_operator_symbol(&t.text));
+        match match_token(cs.as_ref()) {
+            // A minus where a value is expected is the sign of that value. The Unicode minus
+            // is stored as an ASCII `-` so that the value can still be read as a number.
+            1 if active_segment.is_empty() && expect_value => {
+                active_segment.push('-')
+            }
+            // Other operators where a value is expected are kept with the value, except that
+            // a leading plus changes nothing and is dropped, unless nothing follows it. Those
+            // straight after another operator are kept as operators, to be reported.
+            p if p > 0
+                && active_segment.is_empty()
+                && expect_value
+                && (!is_operator_symbol(&cs) || !after_operator) =>
+            {
+                if c != '+' || i + c.len_utf8() == formula.len() {
+                    active_segment.push(c);
+                }
+            }
+            p if p > 0 || p == -1 || p == -2 => {
+                if !active_segment.is_empty() {
+                    tokens.push(Token {
+                        text: active_segment,
+                        position: active_position,
+                    });
+                    active_segment = String::new();
+                }
+                tokens.push(Token {
+                    text: cs,
+                    position: offsets[n],
+                });
+                expect_value = p != -2;
+            }
+            _ => active_segment.push(c),
+        }
+    }
+
+    if !active_segment.is_empty() {
+        tokens.push(Token {
+            text: active_segment,
+            position: active_position,
+        });
+    }
+
+    tokens
+}
+
+#[test]
+fn read_operator_symbols() {
+    for operator in &[Operator::Add, Operator::Subtract, Operator::Multiply, Operator::Divide] {
+        assert_eq!(Some(*operator), Operator::from_symbol(operator.symbol()));
+    }
+    assert_eq!(Some(Operator::Multiply), Operator::from_symbol("×"));
+    assert_eq!(Some(Operator::Subtract), Operator::from_symbol("−"));
+    assert_eq!(None, Operator::from_symbol("("));
+    assert_eq!(None, Operator::from_symbol("2d6"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn split_formulas() {
+    assert_eq!(vec![(0, "1d20 + 5"), (9, " 1d6 + 2")], split_top_level("1d20 + 5; 1d6 + 2", ';'));
+    assert_eq!(vec![(0, "1d20 "), (6, " (1d6 / 2)")], split_top_level("1d20 / (1d6 / 2)", '/'));
+    assert_eq!(vec![(0, "{4d6, 1d8}kh1"), (14, "2")], split_top_level("{4d6, 1d8}kh1,2", ','));
+    assert_eq!(vec![(0, "2d6 − 1")], split_top_level("2d6 − 1", ';'));
+    assert_eq!(vec![(0, "÷"), (2, "")], split_top_level("÷;", ';'));
+}
+
+#[test]
+fn tokenize_formula() {
+    let texts = |input: &str| tokenize(input).into_iter().map(|t| t.text).collect::<Vec<String>>();
+    assert_eq!(vec!["2d6", "+", "-3"], texts("2d6 + -3"));
+    assert_eq!(vec!["-2", "*", "(", "1d4", ")", "+", "1"], texts("-2 * (1d4) + 1"));
+    assert_eq!(vec!["(1d4)d6", "*", "(", "2", ")"], texts("(1d4)d6 * (2)"));
+    assert_eq!(vec!["{4d6,1d8-1}kh3", "+", "2"], texts("{4d6, 1d8 - 1}kh3 + 2"));
+    assert_eq!(vec!["min", "(", "1,2", ")"], texts("min(1,2)"));
+    assert_eq!(vec!["3d6e-1", "-", "1"], texts("3d6e-1 - 1"));
+    assert_eq!(vec!["1d20>=15?2d6:0"], texts("1d20>=15 ? 2d6 : 0"));
+    assert_eq!(vec!["(1d20>=15?2d6:0)", "+", "3"], texts("(1d20>=15 ? 2d6 : 0) + 3"));
+    let starts = |input: &str| tokenize(input).into_iter().map(|t| t.position).collect::<Vec<_>>();
+    assert_eq!(vec![3], starts("   1d2 ? 1 : 0"));
+    assert_eq!(vec!["3(1d6+2)", "+", "1"], texts("3(1d6 + 2) + 1"));
+    assert_eq!(vec!["2d6[cold-iron]", "-", "1d4[xeno]"], texts("2d6[cold-iron] - 1d4[xeno]"));
+    assert_eq!(vec!["3", "*", "(", "1d6", ")"], texts("3 * (1d6)"));
+    assert_eq!(vec!["2d6", "−", "1"], texts("2d6−1"));
+    assert_eq!(vec!["10", "−", "-3"], texts("10 − −3"));
+    assert_eq!(vec!["2d6", "+", "1"], texts("+2d6 + 1"));
+    assert_eq!(vec!["(", "2d6", ")"], texts("(+2d6)"));
+    assert_eq!(vec!["+"], texts("+"));
+    assert_eq!(vec!["2d6", "+", "*", "3"], texts("2d6 +* 3"));
+    assert_eq!(vec!["2d6", "×", "3"], texts("2d6×3"));
+    assert_eq!(vec!["d100", "÷", "15"], texts("d100÷15"));
+    assert_eq!(vec!["2d6", "×", "3", "÷", "2"], texts("2d6 × 3÷2"));
+    assert_eq!(
+        vec![0, 3, 4, 5, 6],
+        tokenize("2d6×3÷2").into_iter().map(|t| t.position).collect::<Vec<usize>>()
+    );
+    assert_eq!(
+        vec![0, 4, 6, 8, 11],
+        tokenize("2d6 + ( 1d4)").into_iter().map(|t| t.position).collect::<Vec<usize>>()
+    );
+}